@@ -0,0 +1,151 @@
+//! Patched frame-of-reference (PFor) packing for Vec of bounded integers.
+//!
+//! A plain `Vec<u16>` with `maxNum=1000` already packs every element into 10 bit lanes.
+//! When most values are small and only a few reach up to `maxNum`, [`PFor`] does better:
+//! every block of 128 lanes gets the width that fits most of its values, and the few
+//! outliers are patched in afterwards as (position, high bits) exceptions.
+//! Signed values are zigzag encoded first, so small magnitudes of either sign stay narrow.
+//!
+//! ## Example
+//! ```rust,ignore
+//! use comprez::bitpack::PFor;
+//!
+//! #[derive(Comprezable, Debug)]
+//! struct Latencies {
+//!     #[maxNum=60000]
+//!     millis: PFor<u32>,
+//! }
+//! ```
+
+use std::fmt::Debug;
+
+use crate::{comprezable::{bit_width, calc_delimeter_size, compress_varint, pack_block, push_bits, read_bits, signed_lane, split_signed_lane, unpack_block, Comprezable}, error::{CompressError, DecompressError}, schema::{record_field, SchemaHasher}, BinaryChunk, Compressed};
+
+const BLOCK_SIZE: usize = 128;
+const WIDTH_BITS: usize = 8;
+const POSITION_BITS: usize = 7;
+
+///Vec of bounded integers packed in blocks of 128 with patched exceptions
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub struct PFor<T>(pub Vec<T>);
+
+impl<T: Comprezable + Clone + Debug> Comprezable for PFor<T> {
+    fn compress(self) -> Result<Compressed, CompressError> {
+        Err(CompressError::create(CompressError::DataNoSupported(String::new())))
+    }
+
+    fn compress_to_binaries(self, max_num: Option<u128>) -> Result<Compressed, CompressError> {
+        let bit_size = match (T::packs_to_lanes(), T::max_binaries(max_num)) {
+            (true, BinaryChunk::Single(bit_size)) => bit_size,
+            _ => return Err(CompressError::create(CompressError::DataNoSupported(String::new()))),
+        };
+
+        let mut res = compress_varint(self.0.len() as u128);
+        for block in self.0.chunks(BLOCK_SIZE) {
+            let lanes = block.iter()
+            .map(|element| element.to_lane(max_num).map(|lane| if T::signed_lanes() { zigzag(lane, bit_size) } else { lane }))
+            .collect::<Result<Vec<u128>, CompressError>>()?;
+            compress_block(&lanes, bit_size, &mut res);
+        }
+        Ok(Compressed::Binaries(res))
+    }
+
+    fn max_binaries(max_num: Option<u128>) -> BinaryChunk {
+        let _field = record_field::<Self>(max_num);
+        T::max_binaries(max_num)
+    }
+
+    fn schema_hash(max_num: Option<u128>) -> u64 {
+        SchemaHasher::new("PFor").field::<T>(max_num).finish()
+    }
+
+    fn decompress(_compressed: Compressed) -> Result<Self, DecompressError> where Self: Sized {
+        Err(DecompressError::create(DecompressError::PrimitiveDataErr(String::new())))
+    }
+
+    fn decompress_from_binaries(compressed: &mut Vec<u8>, bit_size: Option<usize>) -> Result<Self, DecompressError> where Self:Sized {
+        let bit_size = match (T::packs_to_lanes(), bit_size) {
+            (true, Some(bit_size)) => bit_size,
+            _ => return Err(DecompressError::create(DecompressError::PrimitiveDataErr(String::new()))),
+        };
+
+        let count = calc_delimeter_size(compressed, 7)?;
+        //read through a cursor and drain once, draining per lane is quadratic on long vectors
+        let mut cursor = 0;
+        let mut res = vec![];
+        let mut remaining = count;
+        while remaining > 0 {
+            let block_len = remaining.min(BLOCK_SIZE as u128) as usize;
+            for lane in decompress_block(compressed, &mut cursor, block_len, bit_size)? {
+                let lane = if T::signed_lanes() { unzigzag(lane, bit_size) } else { lane };
+                res.push(T::from_lane(lane, bit_size)?);
+            }
+            remaining -= block_len as u128;
+        }
+        compressed.drain( .. cursor);
+        Ok(PFor(res))
+    }
+}
+
+//sign bit lanes make every non-negative value as wide as maxNum, zigzag keeps small magnitudes narrow: 0, -1, 1, -2, ..
+fn zigzag(lane: u128, bit_size: usize) -> u128 {
+    match split_signed_lane(lane, bit_size) {
+        (true, abs) if abs > 0 => abs * 2 - 1,
+        (_, abs) => abs * 2,
+    }
+}
+
+fn unzigzag(lane: u128, bit_size: usize) -> u128 {
+    if lane % 2 == 1 {
+        signed_lane(true, lane / 2 + 1, bit_size)
+    } else {
+        signed_lane(false, lane / 2, bit_size)
+    }
+}
+
+//the width with the fewest bits once the exceptions are paid for
+fn best_width(lanes: &[u128], bit_size: usize) -> usize {
+    let mut widths = lanes.iter().map(|&lane| bit_width(lane)).collect::<Vec<usize>>();
+    widths.sort_unstable();
+
+    (0 ..= bit_size).min_by_key(|&width| {
+        let exceptions = widths.len() - widths.partition_point(|&lane_width| lane_width <= width);
+        lanes.len() * width + exceptions * (POSITION_BITS + bit_size - width)
+    }).unwrap_or(bit_size)
+}
+
+//width, exception count, the low bits of every lane, then (position, high bits) per exception
+fn compress_block(lanes: &[u128], bit_size: usize, res: &mut Vec<u8>) {
+    let width = best_width(lanes, bit_size);
+    let exceptions = lanes.iter().enumerate()
+    .filter(|(_, lane)| bit_width(**lane) > width)
+    .collect::<Vec<(usize, &u128)>>();
+
+    push_bits(res, width as u128, WIDTH_BITS);
+    push_bits(res, exceptions.len() as u128, WIDTH_BITS);
+
+    pack_block(lanes, width, res);
+    for (position, &lane) in exceptions {
+        push_bits(res, position as u128, POSITION_BITS);
+        push_bits(res, lane >> width, bit_size - width);
+    }
+}
+
+fn decompress_block(compressed: &[u8], cursor: &mut usize, block_len: usize, bit_size: usize) -> Result<Vec<u128>, DecompressError> {
+    let width = read_bits(compressed, cursor, WIDTH_BITS)? as usize;
+    let exceptions = read_bits(compressed, cursor, WIDTH_BITS)? as usize;
+    if width > bit_size || exceptions > block_len || (width == bit_size && exceptions > 0) {
+        return Err(DecompressError::create(DecompressError::BinariesToIntErr(format!("block width: {}, exceptions: {}", width, exceptions))))
+    }
+
+    let bits = compressed.get(*cursor .. *cursor + block_len * width).ok_or(DecompressError::create(DecompressError::WrongBytesLength(String::from("Not enough bytes"))))?;
+    let mut lanes = unpack_block(bits, block_len, width);
+    *cursor += block_len * width;
+    for _ in 0 .. exceptions {
+        let position = read_bits(compressed, cursor, POSITION_BITS)? as usize;
+        let high = read_bits(compressed, cursor, bit_size - width)?;
+        let lane = lanes.get_mut(position).ok_or(DecompressError::create(DecompressError::BinariesToIntErr(format!("exception position: {}", position))))?;
+        *lane |= high << width;
+    }
+    Ok(lanes)
+}
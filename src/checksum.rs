@@ -0,0 +1,163 @@
+//! Integrity checksums for whole messages.
+//!
+//! Integer decoding accepts any bits, so a payload corrupted in storage or transit decodes into
+//! garbage instead of failing. With [`Options::checksum`](crate::options::Options) set, a CRC32C
+//! (4 bytes) or xxHash64 (8 bytes) of everything before it is appended to the message, and
+//! `decompress_with` checks it before decoding any field, failing with `ChecksumMismatch`.
+//!
+//! CRC32C is the usual choice for storage; xxHash64 is faster on large messages and catches more.
+//!
+//! ## Example
+//! ```rust,ignore
+//! use comprez::{checksum::Checksum, options::Options};
+//!
+//! let options = Options { checksum: Some(Checksum::Crc32c), header: true, ..Options::default() };
+//! let bytes = record.compress_with(&options)?.to_bytes();
+//! match Record::decompress_with(Compressed::from_bytes(bytes), &options) {
+//!     Err(DecompressError::ChecksumMismatch(_)) => restore_from_replica(),
+//!     ..
+//! }
+//! ```
+
+use crate::{error::DecompressError, Compressed};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Checksum {
+    ///CRC-32 with the Castagnoli polynomial, as in iSCSI, ext4 and SSE4.2
+    Crc32c,
+    ///xxHash64 with seed 0
+    XxHash64,
+}
+
+impl Checksum {
+    ///Length of the trailer in bytes
+    pub fn trailer_len(&self) -> usize {
+        match self {
+            Checksum::Crc32c => 4,
+            Checksum::XxHash64 => 8,
+        }
+    }
+
+    ///The trailer for `bytes`, little endian
+    pub fn digest(&self, bytes: &[u8]) -> Vec<u8> {
+        match self {
+            Checksum::Crc32c => crc32c(bytes).to_le_bytes().to_vec(),
+            Checksum::XxHash64 => xxhash64(bytes, 0).to_le_bytes().to_vec(),
+        }
+    }
+}
+
+//message padded to whole bytes, then the checksum of those bytes
+pub(crate) fn append_checksum(message: Compressed, checksum: Checksum) -> Compressed {
+    let mut bytes = message.to_bytes();
+    bytes.extend(checksum.digest(&bytes));
+    Compressed::Bytes(bytes)
+}
+
+//the message without its trailer, once the trailer matches
+pub(crate) fn verify_checksum(compressed: Compressed, checksum: Checksum) -> Result<Compressed, DecompressError> {
+    let mut bytes = compressed.to_bytes();
+    if bytes.len() < checksum.trailer_len() {
+        return Err(DecompressError::create(DecompressError::ChecksumMismatch(format!("{} bytes is shorter than a {:?} checksum", bytes.len(), checksum))))
+    }
+    let trailer = bytes.split_off(bytes.len() - checksum.trailer_len());
+    let expected = checksum.digest(&bytes);
+    if trailer != expected {
+        return Err(DecompressError::create(DecompressError::ChecksumMismatch(format!("{:?} {:02x?}, computed {:02x?}", checksum, trailer, expected))))
+    }
+    Ok(Compressed::Bytes(bytes))
+}
+
+const CRC32C_POLY: u32 = 0x82F6_3B78;
+const CRC32C_TABLE: [u32; 256] = crc32c_table();
+
+const fn crc32c_table() -> [u32; 256] {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 == 1 { (crc >> 1) ^ CRC32C_POLY } else { crc >> 1 };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+}
+
+pub fn crc32c(bytes: &[u8]) -> u32 {
+    !bytes.iter().fold(!0u32, |crc, &byte| {
+        CRC32C_TABLE[((crc ^ byte as u32) & 0xFF) as usize] ^ (crc >> 8)
+    })
+}
+
+const PRIME64_1: u64 = 0x9E37_79B1_85EB_CA87;
+const PRIME64_2: u64 = 0xC2B2_AE3D_27D4_EB4F;
+const PRIME64_3: u64 = 0x1656_67B1_9E37_79F9;
+const PRIME64_4: u64 = 0x85EB_CA77_C2B2_AE63;
+const PRIME64_5: u64 = 0x27D4_EB2F_1656_67C5;
+
+fn read_u64(bytes: &[u8]) -> u64 {
+    u64::from_le_bytes(bytes[.. 8].try_into().unwrap())
+}
+
+fn read_u32(bytes: &[u8]) -> u32 {
+    u32::from_le_bytes(bytes[.. 4].try_into().unwrap())
+}
+
+fn xxh64_round(acc: u64, input: u64) -> u64 {
+    acc.wrapping_add(input.wrapping_mul(PRIME64_2)).rotate_left(31).wrapping_mul(PRIME64_1)
+}
+
+fn xxh64_merge(acc: u64, value: u64) -> u64 {
+    (acc ^ xxh64_round(0, value)).wrapping_mul(PRIME64_1).wrapping_add(PRIME64_4)
+}
+
+pub fn xxhash64(bytes: &[u8], seed: u64) -> u64 {
+    let mut rest = bytes;
+    let mut hash = if bytes.len() >= 32 {
+        let mut acc = [
+            seed.wrapping_add(PRIME64_1).wrapping_add(PRIME64_2),
+            seed.wrapping_add(PRIME64_2),
+            seed,
+            seed.wrapping_sub(PRIME64_1),
+        ];
+        while rest.len() >= 32 {
+            for (i, lane) in acc.iter_mut().enumerate() {
+                *lane = xxh64_round(*lane, read_u64(&rest[i * 8 ..]));
+            }
+            rest = &rest[32 ..];
+        }
+        let hash = acc[0].rotate_left(1)
+        .wrapping_add(acc[1].rotate_left(7))
+        .wrapping_add(acc[2].rotate_left(12))
+        .wrapping_add(acc[3].rotate_left(18));
+        acc.iter().fold(hash, |hash, &lane| xxh64_merge(hash, lane))
+    } else {
+        seed.wrapping_add(PRIME64_5)
+    };
+    hash = hash.wrapping_add(bytes.len() as u64);
+
+    while rest.len() >= 8 {
+        hash ^= xxh64_round(0, read_u64(rest));
+        hash = hash.rotate_left(27).wrapping_mul(PRIME64_1).wrapping_add(PRIME64_4);
+        rest = &rest[8 ..];
+    }
+    if rest.len() >= 4 {
+        hash ^= (read_u32(rest) as u64).wrapping_mul(PRIME64_1);
+        hash = hash.rotate_left(23).wrapping_mul(PRIME64_2).wrapping_add(PRIME64_3);
+        rest = &rest[4 ..];
+    }
+    for &byte in rest {
+        hash ^= (byte as u64).wrapping_mul(PRIME64_5);
+        hash = hash.rotate_left(11).wrapping_mul(PRIME64_1);
+    }
+
+    hash ^= hash >> 33;
+    hash = hash.wrapping_mul(PRIME64_2);
+    hash ^= hash >> 29;
+    hash = hash.wrapping_mul(PRIME64_3);
+    hash ^ (hash >> 32)
+}
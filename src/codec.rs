@@ -0,0 +1,405 @@
+//! Byte codecs behind `Vec<u8>`.
+//!
+//! Every `Vec<u8>` is run through a [`ByteCodec`] and stored raw instead whenever that comes out
+//! smaller. The codec is recorded in the stream, so decoding needs no configuration.
+//!
+//! | Codec | Cargo feature | Backend |
+//! |---|---|---|
+//! | [`Codec::Store`] | - | bytes as they are |
+//! | [`Codec::Lz4`] | `lz4` (default) | lz4_flex |
+//! | [`Codec::Deflate`] | `deflate` | miniz_oxide, levels 0 - 10 |
+//! | [`Codec::Zstd`] | `zstd` | ruzstd, level 0 stores, any other level is zstd level 1 |
+//!
+//! The codec is picked per message with [`with_codec`] (or [`set_default_codec`] for the thread),
+//! or per field with [`Coded`].
+//!
+//! Codec ids are 2 bits, the last value escaping to a varint id, so codecs added later are
+//! written past the escape and never change how existing streams read.
+//!
+//! ## Example
+//! ```rust,ignore
+//! use comprez::codec::{with_codec, Codec, Coded};
+//!
+//! #[derive(Comprezable, Debug)]
+//! struct Upload {
+//!     thumbnail: Vec<u8>,
+//!     document: Coded,
+//! }
+//!
+//! let upload = Upload {
+//!     thumbnail,
+//!     document: Coded { data: document, codec: Codec::Deflate { level: 9 } },
+//! };
+//! let compressed = with_codec(Codec::Zstd { level: 1 }, || upload.compress())?;
+//! ```
+
+use std::cell::Cell;
+
+use crate::{comprezable::{calc_delimeter_size, compress_varint, push_bits, take_bits, Comprezable}, error::{CompressError, DecompressError}, schema::{record_field, SchemaHasher}, BinaryChunk, Compressed};
+
+const CODEC_BITS: usize = 2;
+//the last 2 bit id is an escape, ids from it on are written as the escape and a varint of id - EXTENDED_ID
+const EXTENDED_ID: u128 = 3;
+//LZ4 with the dictionary `crate::trained::with_dictionary` set, the first extended id
+const TRAINED_LZ4_ID: u128 = EXTENDED_ID;
+
+pub const DEFAULT_DEFLATE_LEVEL: u8 = 6;
+pub const DEFAULT_ZSTD_LEVEL: u8 = 1;
+
+//magic numbers of formats that are compressed already, a codec only adds to them
+const PRECOMPRESSED_MAGICS: [&[u8]; 9] = [
+    &[0xFF, 0xD8, 0xFF], //JPEG
+    &[0x89, b'P', b'N', b'G'], //PNG
+    &[0x1F, 0x8B], //gzip
+    &[0x28, 0xB5, 0x2F, 0xFD], //zstd
+    &[0x04, 0x22, 0x4D, 0x18], //LZ4 frame
+    &[b'P', b'K', 0x03, 0x04], //zip
+    &[0xFD, b'7', b'z', b'X', b'Z', 0x00], //xz
+    b"BZh", //bzip2
+    &[b'7', b'z', 0xBC, 0xAF, 0x27, 0x1C], //7z
+];
+
+pub(crate) fn is_precompressed(bytes: &[u8]) -> bool {
+    PRECOMPRESSED_MAGICS.iter().any(|magic| bytes.starts_with(magic))
+}
+
+//the output size an LZ4 block declares up front, checked before lz4_flex allocates it
+#[cfg(feature = "lz4")]
+pub(crate) fn check_prepended_size(block: &[u8]) -> Result<(), DecompressError> {
+    use crate::comprezable::check_decode_len;
+
+    let size = block.get(..4).ok_or(DecompressError::create(DecompressError::WrongBytesLength(format!("{} bytes for an LZ4 size", block.len()))))?;
+    check_decode_len(u32::from_le_bytes(size.try_into().unwrap()) as u128).map(|_| ())
+}
+
+///Compresses and restores a whole byte buffer
+pub trait ByteCodec {
+    fn encode(&self, bytes: &[u8]) -> Result<Vec<u8>, CompressError>;
+
+    fn decode(&self, bytes: &[u8]) -> Result<Vec<u8>, DecompressError>;
+}
+
+///Bytes as they are
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Store;
+
+impl ByteCodec for Store {
+    fn encode(&self, bytes: &[u8]) -> Result<Vec<u8>, CompressError> {
+        Ok(bytes.to_vec())
+    }
+
+    fn decode(&self, bytes: &[u8]) -> Result<Vec<u8>, DecompressError> {
+        Ok(bytes.to_vec())
+    }
+}
+
+///Credit to LZ4 flex library for this compression
+#[cfg(feature = "lz4")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Lz4;
+
+#[cfg(feature = "lz4")]
+impl ByteCodec for Lz4 {
+    fn encode(&self, bytes: &[u8]) -> Result<Vec<u8>, CompressError> {
+        Ok(lz4_flex::compress_prepend_size(bytes))
+    }
+
+    fn decode(&self, bytes: &[u8]) -> Result<Vec<u8>, DecompressError> {
+        check_prepended_size(bytes)?;
+        lz4_flex::decompress_size_prepended(bytes).map_err(|err| {
+            DecompressError::create(DecompressError::FromBytesErr(err.to_string()))
+        })
+    }
+}
+
+///Raw deflate through miniz_oxide
+#[cfg(feature = "deflate")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Deflate {
+    ///0 (store) to 10 (smallest)
+    pub level: u8,
+}
+
+#[cfg(feature = "deflate")]
+impl ByteCodec for Deflate {
+    fn encode(&self, bytes: &[u8]) -> Result<Vec<u8>, CompressError> {
+        Ok(miniz_oxide::deflate::compress_to_vec(bytes, self.level.min(10)))
+    }
+
+    fn decode(&self, bytes: &[u8]) -> Result<Vec<u8>, DecompressError> {
+        use miniz_oxide::inflate::TINFLStatus;
+        use crate::comprezable::max_decode_len;
+
+        //raw deflate never says how much it expands to, so stop inflating at the limit
+        let max_len = max_decode_len();
+        miniz_oxide::inflate::decompress_to_vec_with_limit(bytes, max_len).map_err(|err| match err.status {
+            TINFLStatus::HasMoreOutput => DecompressError::create(DecompressError::LengthLimitExceeded(format!("inflated past the limit of {} bytes", max_len))),
+            _ => DecompressError::create(DecompressError::FromBytesErr(err.to_string())),
+        })
+    }
+}
+
+///Zstandard frames through ruzstd, which only implements its fastest level so far
+#[cfg(feature = "zstd")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Zstd {
+    ///0 wraps the bytes uncompressed, anything else is zstd level 1
+    pub level: u8,
+}
+
+#[cfg(feature = "zstd")]
+impl ByteCodec for Zstd {
+    fn encode(&self, bytes: &[u8]) -> Result<Vec<u8>, CompressError> {
+        use ruzstd::encoding::{compress_to_vec, CompressionLevel};
+
+        let level = match self.level {
+            0 => CompressionLevel::Uncompressed,
+            _ => CompressionLevel::Fastest,
+        };
+        Ok(compress_to_vec(bytes, level))
+    }
+
+    fn decode(&self, bytes: &[u8]) -> Result<Vec<u8>, DecompressError> {
+        use std::io::Read;
+        use crate::comprezable::max_decode_len;
+
+        let to_err = |err: String| DecompressError::create(DecompressError::FromBytesErr(err));
+        let decoder = ruzstd::decoding::StreamingDecoder::new(bytes).map_err(|err| to_err(err.to_string()))?;
+        //read one byte past the limit to tell a frame that fits from one that does not
+        let max_len = max_decode_len();
+        let mut res = vec![];
+        decoder.take(max_len as u64 + 1).read_to_end(&mut res).map_err(|err| to_err(err.to_string()))?;
+        if res.len() > max_len {
+            return Err(DecompressError::create(DecompressError::LengthLimitExceeded(format!("decoded past the limit of {} bytes", max_len))))
+        }
+        Ok(res)
+    }
+}
+
+///The codecs a stream can name, whether or not this build enables them
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Codec {
+    Store,
+    Lz4,
+    Deflate { level: u8 },
+    Zstd { level: u8 },
+}
+
+impl Default for Codec {
+    ///LZ4, or Store when the `lz4` feature is off
+    fn default() -> Self {
+        if cfg!(feature = "lz4") {
+            Codec::Lz4
+        } else {
+            Codec::Store
+        }
+    }
+}
+
+impl Codec {
+    fn name(self) -> &'static str {
+        match self {
+            Codec::Store => "store",
+            Codec::Lz4 => "lz4",
+            Codec::Deflate { .. } => "deflate",
+            Codec::Zstd { .. } => "zstd",
+        }
+    }
+
+    //Store is written as the raw flag instead of an id
+    fn id(self) -> Option<u128> {
+        match self {
+            Codec::Store => None,
+            Codec::Lz4 => Some(0),
+            Codec::Deflate { .. } => Some(1),
+            Codec::Zstd { .. } => Some(2),
+        }
+    }
+
+    fn from_id(id: u128) -> Result<Self, DecompressError> {
+        match id {
+            0 => Ok(Codec::Lz4),
+            1 => Ok(Codec::Deflate { level: DEFAULT_DEFLATE_LEVEL }),
+            2 => Ok(Codec::Zstd { level: DEFAULT_ZSTD_LEVEL }),
+            _ => Err(DecompressError::create(DecompressError::UnsupportedCodec(format!("codec id {}", id)))),
+        }
+    }
+
+    fn backend(self) -> Option<Box<dyn ByteCodec>> {
+        match self {
+            Codec::Store => Some(Box::new(Store)),
+            #[cfg(feature = "lz4")]
+            Codec::Lz4 => Some(Box::new(Lz4)),
+            #[cfg(feature = "deflate")]
+            Codec::Deflate { level } => Some(Box::new(Deflate { level })),
+            #[cfg(feature = "zstd")]
+            Codec::Zstd { level } => Some(Box::new(Zstd { level })),
+            #[allow(unreachable_patterns)]
+            _ => None,
+        }
+    }
+}
+
+impl ByteCodec for Codec {
+    fn encode(&self, bytes: &[u8]) -> Result<Vec<u8>, CompressError> {
+        match self.backend() {
+            Some(backend) => backend.encode(bytes),
+            None => Err(CompressError::create(CompressError::EncodeErr(format!("Compress Error; {} codec needs the `{}` feature", self.name(), self.name())))),
+        }
+    }
+
+    fn decode(&self, bytes: &[u8]) -> Result<Vec<u8>, DecompressError> {
+        match self.backend() {
+            Some(backend) => backend.decode(bytes),
+            None => Err(DecompressError::create(DecompressError::UnsupportedCodec(format!("{}, enable the `{}` feature", self.name(), self.name())))),
+        }
+    }
+}
+
+thread_local! {
+    static DEFAULT_CODEC: Cell<Codec> = Cell::new(Codec::default());
+}
+
+///Sets the codec plain `Vec<u8>` uses on this thread
+pub fn set_default_codec(codec: Codec) {
+    DEFAULT_CODEC.with(|default| default.set(codec));
+}
+
+///The codec plain `Vec<u8>` uses on this thread
+pub fn default_codec() -> Codec {
+    DEFAULT_CODEC.with(|default| default.get())
+}
+
+struct CodecGuard(Codec);
+
+impl Drop for CodecGuard {
+    fn drop(&mut self) {
+        set_default_codec(self.0);
+    }
+}
+
+///Runs `f` (usually one `compress()`) with `codec` as the default, restoring the previous one after
+pub fn with_codec<R>(codec: Codec, f: impl FnOnce() -> R) -> R {
+    let _guard = CodecGuard(default_codec());
+    set_default_codec(codec);
+    f()
+}
+
+//ids below EXTENDED_ID in 2 bits, every other one as the escape and a varint, so new codecs never run out of ids
+pub(crate) fn push_codec_id(res: &mut Vec<u8>, id: u128) {
+    push_bits(res, id.min(EXTENDED_ID), CODEC_BITS);
+    if id >= EXTENDED_ID {
+        res.extend(compress_varint(id - EXTENDED_ID));
+    }
+}
+
+pub(crate) fn take_codec_id(compressed: &mut Vec<u8>) -> Result<u128, DecompressError> {
+    match take_bits(compressed, CODEC_BITS)? {
+        EXTENDED_ID => Ok(EXTENDED_ID + calc_delimeter_size(compressed, 7)?),
+        id => Ok(id),
+    }
+}
+
+//the codec id and output when it is smaller than the bytes themselves, None to store them as they are
+pub(crate) fn encode_bytes(bytes: &[u8], codec: Codec) -> Result<Option<(u128, Vec<u8>)>, CompressError> {
+    let id = match (codec.id(), is_precompressed(bytes)) {
+        (Some(id), false) => id,
+        _ => return Ok(None),
+    };
+    let (id, encoded) = match (codec, trained_dictionary()) {
+        #[cfg(feature = "lz4")]
+        (Codec::Lz4, Some(dictionary)) => (TRAINED_LZ4_ID, dictionary.encode(bytes)),
+        _ => (id, codec.encode(bytes)?),
+    };
+    Ok(Some((id, encoded)).filter(|(_, encoded)| encoded.len() < bytes.len()))
+}
+
+pub(crate) fn decode_bytes(id: u128, bytes: &[u8]) -> Result<(Vec<u8>, Codec), DecompressError> {
+    if id == TRAINED_LZ4_ID {
+        #[cfg(feature = "lz4")]
+        return crate::trained::decode_with_active(bytes).map(|bytes| (bytes, Codec::Lz4));
+        #[cfg(not(feature = "lz4"))]
+        return Err(DecompressError::create(DecompressError::UnsupportedCodec(String::from("trained lz4, enable the `lz4` feature"))));
+    }
+    let codec = Codec::from_id(id)?;
+    codec.decode(bytes).map(|bytes| (bytes, codec))
+}
+
+//flag 0 with the raw bytes, or flag 1 with the codec id and its output, each led by its byte length
+pub(crate) fn compress_bytes(bytes: Vec<u8>, codec: Codec) -> Result<Compressed, CompressError> {
+    //the raw length prefix is never longer than the encoded one, so only the bytes need comparing
+    if let Some((id, encoded)) = encode_bytes(&bytes, codec)? {
+        let mut res = vec![1];
+        push_codec_id(&mut res, id);
+        res.extend(compress_varint(encoded.len() as u128));
+        return Ok(Compressed::Binaries(res).combine(Compressed::Bytes(encoded)))
+    }
+
+    let mut res = vec![0];
+    res.extend(compress_varint(bytes.len() as u128));
+    Ok(Compressed::Binaries(res).combine(Compressed::Bytes(bytes)))
+}
+
+pub(crate) fn decompress_bytes(compressed: &mut Vec<u8>) -> Result<(Vec<u8>, Codec), DecompressError> {
+    let id = match take_bits(compressed, 1)? {
+        1 => Some(take_codec_id(compressed)?),
+        _ => None,
+    };
+
+    //size (in bytes) of the stored vec
+    let meta = calc_delimeter_size(compressed, 7)?;
+    if meta > (compressed.len() / 8) as u128 {
+        return Err(DecompressError::create(DecompressError::WrongBytesLength(String::from("length of compressed bytes is shorter than meta-length of compressed vector, AKA meta-length out of bound"))))
+    }
+    let bytes = Compressed::Binaries(compressed.drain( .. meta as usize * 8).collect()).to_bytes();
+
+    match id {
+        Some(id) => decode_bytes(id, &bytes),
+        None => Ok((bytes, Codec::Store)),
+    }
+}
+
+#[cfg(feature = "lz4")]
+fn trained_dictionary() -> Option<crate::trained::TrainedDict> {
+    crate::trained::active_dictionary()
+}
+
+#[cfg(not(feature = "lz4"))]
+fn trained_dictionary() -> Option<()> {
+    None
+}
+
+///Byte buffer compressed with its own codec instead of the default one
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct Coded {
+    pub data: Vec<u8>,
+    ///Decodes as the codec that was actually written, at its default level
+    pub codec: Codec,
+}
+
+impl Comprezable for Coded {
+    fn compress(self) -> Result<Compressed, CompressError> {
+        Err(CompressError::create(CompressError::DataNoSupported(String::new())))
+    }
+
+    fn compress_to_binaries(self, _max_num: Option<u128>) -> Result<Compressed, CompressError> {
+        compress_bytes(self.data, self.codec)
+    }
+
+    fn max_binaries(max_num: Option<u128>) -> BinaryChunk {
+        let _field = record_field::<Self>(max_num);
+        BinaryChunk::Delimeter
+    }
+
+    fn schema_hash(_max_num: Option<u128>) -> u64 {
+        SchemaHasher::new("Coded").finish()
+    }
+
+    fn decompress(_compressed: Compressed) -> Result<Self, DecompressError> where Self: Sized {
+        Err(DecompressError::create(DecompressError::PrimitiveDataErr(String::new())))
+    }
+
+    fn decompress_from_binaries(compressed: &mut Vec<u8>, _bit_size: Option<usize>) -> Result<Self, DecompressError> where Self:Sized {
+        decompress_bytes(compressed).map(|(data, codec)| Coded { data, codec })
+    }
+}
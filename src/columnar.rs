@@ -0,0 +1,98 @@
+//! Columnar (struct-of-arrays) layout for Vec of structs.
+//!
+//! `Vec<MyStruct>` is compressed row by row. Wrapping it in [`Columns`] compresses one
+//! column per field instead, so every column can use the encoding that suits it best
+//! (`Delta`, `Rle`, `Sparse`, a `Vec<u8>` through LZ4, ...), and similar values end up side by side.
+//!
+//! The column layout is a Comprezable struct of its own, tied to the row type by [`Columnar`].
+//!
+//! ## Example
+//! ```rust,ignore
+//! use comprez::{columnar::{Columnar, Columns, check_column_lengths}, delta::Delta, rle::Rle};
+//!
+//! #[derive(Comprezable, Debug, Clone)]
+//! struct Trade {
+//!     #[maxNum=1000000]
+//!     id: u32,
+//!     #[maxNum=10]
+//!     venue: u16,
+//! }
+//!
+//! #[derive(Comprezable, Debug, Clone)]
+//! struct TradeColumns {
+//!     id: Delta<u32>,
+//!     #[maxNum=10]
+//!     venue: Rle<u16>,
+//! }
+//!
+//! impl Columnar for Trade {
+//!     type Columns = TradeColumns;
+//!
+//!     fn to_columns(rows: Vec<Self>) -> TradeColumns {
+//!         TradeColumns {
+//!             id: Delta(rows.iter().map(|row| row.id).collect()),
+//!             venue: Rle(rows.iter().map(|row| row.venue).collect()),
+//!         }
+//!     }
+//!
+//!     fn from_columns(columns: TradeColumns) -> Result<Vec<Self>, DecompressError> {
+//!         check_column_lengths(&[columns.id.0.len(), columns.venue.0.len()])?;
+//!         Ok(columns.id.0.into_iter().zip(columns.venue.0).map(|(id, venue)| Trade { id, venue }).collect())
+//!     }
+//! }
+//!
+//! let trades: Columns<Trade> = Columns(vec![..]);
+//! ```
+
+use crate::{comprezable::{nested_decode, nested_max_binaries, Comprezable}, error::{CompressError, DecompressError}, schema::{record_field, SchemaHasher}, BinaryChunk, Compressed};
+
+///Splits rows into a column layout and joins them back
+pub trait Columnar: Sized {
+    ///Struct holding one Vec (or Vec wrapper) per field
+    type Columns: Comprezable;
+
+    fn to_columns(rows: Vec<Self>) -> Self::Columns;
+
+    fn from_columns(columns: Self::Columns) -> Result<Vec<Self>, DecompressError>;
+}
+
+///Vec of rows compressed column by column
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub struct Columns<T>(pub Vec<T>);
+
+impl<T: Columnar> Comprezable for Columns<T> {
+    fn compress(self) -> Result<Compressed, CompressError> {
+        Err(CompressError::create(CompressError::DataNoSupported(String::new())))
+    }
+
+    fn compress_to_binaries(self, _max_num: Option<u128>) -> Result<Compressed, CompressError> {
+        T::to_columns(self.0).compress_to_binaries(None)
+    }
+
+    fn max_binaries(max_num: Option<u128>) -> BinaryChunk {
+        let _field = record_field::<Self>(max_num);
+        nested_max_binaries::<T::Columns>(None)
+    }
+
+    fn schema_hash(max_num: Option<u128>) -> u64 {
+        SchemaHasher::new("Columns").field::<T::Columns>(max_num).finish()
+    }
+
+    fn decompress(_compressed: Compressed) -> Result<Self, DecompressError> where Self: Sized {
+        Err(DecompressError::create(DecompressError::PrimitiveDataErr(String::new())))
+    }
+
+    fn decompress_from_binaries(compressed: &mut Vec<u8>, _bit_size: Option<usize>) -> Result<Self, DecompressError> where Self:Sized {
+        let columns = nested_decode(|| T::Columns::decompress_from_binaries(compressed, None))?;
+        T::from_columns(columns).map(Columns)
+    }
+}
+
+///Row count shared by every column, errors when the columns disagree
+pub fn check_column_lengths(lengths: &[usize]) -> Result<usize, DecompressError> {
+    let rows = lengths.first().copied().unwrap_or(0);
+    if lengths.iter().any(|&len| len != rows) {
+        return Err(DecompressError::create(DecompressError::WrongBytesLength(format!("column lengths differ: {:?}", lengths))))
+    }
+    Ok(rows)
+}
@@ -1,4 +1,4 @@
-use crate::{error::{DecompressError, CompressError}, Compressed, BinaryChunk};
+use crate::{error::{DecompressError, CompressError}, codec, options::{self, Options}, schema::{self, record_field, SchemaHasher}, session, shared, Compressed, BinaryChunk};
 
 pub trait Comprezable<Rhs = Self> {
     ///Compress function
@@ -27,6 +27,40 @@ pub trait Comprezable<Rhs = Self> {
 
     fn decompress_from_binaries(compressed: &mut Vec<u8>, bit_size: Option<usize>) -> Result<Self, DecompressError> where Self:Sized;
 
+    ///`compress` with whole-message options, see `crate::options`
+    fn compress_with(self, options: &Options) -> Result<Compressed, CompressError> where Self: Sized + Comprezable {
+        options::compress_message(self, options)
+    }
+
+    ///`decompress` of a message compressed by `compress_with` with the same options
+    fn decompress_with(compressed: Compressed, options: &Options) -> Result<Self, DecompressError> where Self: Sized + Comprezable {
+        options::decompress_message(compressed, options)
+    }
+
+    ///Stable hash of this type's encoding, see `crate::schema`
+    fn schema_hash(max_num: Option<u128>) -> u64 where Self: Sized {
+        schema::struct_hash(std::any::type_name::<Self>(), || Self::max_binaries(max_num))
+    }
+
+    ///True for fixed width integers, Vec then packs and unpacks them in bulk through `to_lane` and `from_lane`
+    fn packs_to_lanes() -> bool where Self: Sized {
+        false
+    }
+
+    ///True when the lanes lead with a sign bit, 1 for positive, like the signed integers
+    fn signed_lanes() -> bool where Self: Sized {
+        false
+    }
+
+    ///The same bits `compress_to_binaries` gives, as one integer
+    fn to_lane(&self, _max_num: Option<u128>) -> Result<u128, CompressError> {
+        Err(CompressError::create(CompressError::DataNoSupported(String::new())))
+    }
+
+    fn from_lane(_lane: u128, _bit_size: usize) -> Result<Self, DecompressError> where Self: Sized {
+        Err(DecompressError::create(DecompressError::PrimitiveDataErr(String::new())))
+    }
+
 }
 
 /* 
@@ -94,10 +128,15 @@ impl Comprezable for u16 {
     }
 
     fn max_binaries(max_num: Option<u128>) -> BinaryChunk {
+        let _field = record_field::<Self>(max_num);
         let max_num = max_num.unwrap() as Self;
         BinaryChunk::Single(find_mult_8_bit_size(max_num, 8, 0, 2) + 3)
     }
 
+    fn schema_hash(max_num: Option<u128>) -> u64 {
+        SchemaHasher::new("u16").bound(max_num).finish()
+    }
+
     fn decompress(_compressed: Compressed) -> Result<u16, DecompressError> {
         Err(DecompressError::create(DecompressError::PrimitiveDataErr(String::new())))
     }
@@ -123,6 +162,24 @@ impl Comprezable for u16 {
     
         Ok(mult_8 + remainder)
     }
+
+    fn packs_to_lanes() -> bool {
+        true
+    }
+
+    fn to_lane(&self, max_num: Option<u128>) -> Result<u128, CompressError> {
+        let max_num = max_num.unwrap() as Self;
+        if *self > max_num {
+            return Err(CompressError::create(CompressError::Overflow(format!("given: {}, max-num: {}", self, max_num))))
+        }
+        Ok(*self as u128)
+    }
+
+    fn from_lane(lane: u128, _bit_size: usize) -> Result<Self, DecompressError> {
+        Self::try_from(lane).map_err(|_| {
+            DecompressError::create(DecompressError::BinariesToIntErr(format!("Lane given: {}", lane)))
+        })
+    }
 }
 
 impl Comprezable for u32 {
@@ -143,10 +200,15 @@ impl Comprezable for u32 {
     }
 
     fn max_binaries(max_num: Option<u128>) -> BinaryChunk {
+        let _field = record_field::<Self>(max_num);
         let max_num = max_num.unwrap() as Self;
         BinaryChunk::Single(find_mult_8_bit_size(max_num, 8, 0, 2) + 3)
     }
 
+    fn schema_hash(max_num: Option<u128>) -> u64 {
+        SchemaHasher::new("u32").bound(max_num).finish()
+    }
+
     fn decompress(_compressed: Compressed) -> Result<u32, DecompressError> {
         Err(DecompressError::create(DecompressError::PrimitiveDataErr(String::new())))
     }
@@ -172,6 +234,24 @@ impl Comprezable for u32 {
     
         Ok(mult_8 + remainder)
     }
+
+    fn packs_to_lanes() -> bool {
+        true
+    }
+
+    fn to_lane(&self, max_num: Option<u128>) -> Result<u128, CompressError> {
+        let max_num = max_num.unwrap() as Self;
+        if *self > max_num {
+            return Err(CompressError::create(CompressError::Overflow(format!("given: {}, max-num: {}", self, max_num))))
+        }
+        Ok(*self as u128)
+    }
+
+    fn from_lane(lane: u128, _bit_size: usize) -> Result<Self, DecompressError> {
+        Self::try_from(lane).map_err(|_| {
+            DecompressError::create(DecompressError::BinariesToIntErr(format!("Lane given: {}", lane)))
+        })
+    }
 }
 impl Comprezable for u64 {
     fn compress(self) -> Result<Compressed, CompressError> {
@@ -191,10 +271,15 @@ impl Comprezable for u64 {
     }
 
     fn max_binaries(max_num: Option<u128>) -> BinaryChunk {
+        let _field = record_field::<Self>(max_num);
         let max_num = max_num.unwrap() as Self;
         BinaryChunk::Single(find_mult_8_bit_size(max_num, 8, 0, 2) + 3)
     }
 
+    fn schema_hash(max_num: Option<u128>) -> u64 {
+        SchemaHasher::new("u64").bound(max_num).finish()
+    }
+
     fn decompress(_compressed: Compressed) -> Result<u64, DecompressError> {
         Err(DecompressError::create(DecompressError::PrimitiveDataErr(String::new())))
     }
@@ -220,6 +305,24 @@ impl Comprezable for u64 {
     
         Ok(mult_8 + remainder)
     }
+
+    fn packs_to_lanes() -> bool {
+        true
+    }
+
+    fn to_lane(&self, max_num: Option<u128>) -> Result<u128, CompressError> {
+        let max_num = max_num.unwrap() as Self;
+        if *self > max_num {
+            return Err(CompressError::create(CompressError::Overflow(format!("given: {}, max-num: {}", self, max_num))))
+        }
+        Ok(*self as u128)
+    }
+
+    fn from_lane(lane: u128, _bit_size: usize) -> Result<Self, DecompressError> {
+        Self::try_from(lane).map_err(|_| {
+            DecompressError::create(DecompressError::BinariesToIntErr(format!("Lane given: {}", lane)))
+        })
+    }
 }
 impl Comprezable for u128 {
     fn compress(self) -> Result<Compressed, CompressError> {
@@ -238,10 +341,15 @@ impl Comprezable for u128 {
     }
 
     fn max_binaries(max_num: Option<u128>) -> BinaryChunk {
+        let _field = record_field::<Self>(max_num);
         let max_num = max_num.unwrap() as Self;
         BinaryChunk::Single(find_mult_8_bit_size(max_num, 8, 0, 2) + 3)
     }
 
+    fn schema_hash(max_num: Option<u128>) -> u64 {
+        SchemaHasher::new("u128").bound(max_num).finish()
+    }
+
     fn decompress(_compressed: Compressed) -> Result<u128, DecompressError> {
         Err(DecompressError::create(DecompressError::PrimitiveDataErr(String::new())))
     }
@@ -267,6 +375,22 @@ impl Comprezable for u128 {
     
         Ok(mult_8 + remainder)
     }
+
+    fn packs_to_lanes() -> bool {
+        true
+    }
+
+    fn to_lane(&self, max_num: Option<u128>) -> Result<u128, CompressError> {
+        let max_num = max_num.unwrap() as Self;
+        if *self > max_num {
+            return Err(CompressError::create(CompressError::Overflow(format!("given: {}, max-num: {}", self, max_num))))
+        }
+        Ok(*self)
+    }
+
+    fn from_lane(lane: u128, _bit_size: usize) -> Result<Self, DecompressError> {
+        Ok(lane)
+    }
 }
 
  
@@ -297,10 +421,15 @@ impl Comprezable for i8 {
     }
 
     fn max_binaries(max_num: Option<u128>) -> BinaryChunk {
+        let _field = record_field::<Self>(max_num);
         let max_num = max_num.unwrap() as Self;
         BinaryChunk::Single(find_mult_8_bit_size(max_num.abs(), 8, 0, 2) + 4)
     }
 
+    fn schema_hash(max_num: Option<u128>) -> u64 {
+        SchemaHasher::new("i8").bound(max_num).finish()
+    }
+
     fn decompress(_compressed: Compressed) -> Result<i8, DecompressError> {
         Err(DecompressError::create(DecompressError::PrimitiveDataErr(String::new())))
     }
@@ -337,6 +466,35 @@ impl Comprezable for i8 {
     
         Ok(res)
     }
+
+    fn packs_to_lanes() -> bool {
+        true
+    }
+
+    fn signed_lanes() -> bool {
+        true
+    }
+
+    fn to_lane(&self, max_num: Option<u128>) -> Result<u128, CompressError> {
+        let max_num = max_num.unwrap() as Self;
+        if self.abs() > max_num {
+            return Err(CompressError::create(CompressError::Overflow(format!("given: {}, max-num: {}", self, max_num))))
+        }
+        let bit_size = find_mult_8_bit_size(max_num.abs(), 8, 0, 2) + 4;
+        Ok(signed_lane(*self < 0, self.unsigned_abs() as u128, bit_size))
+    }
+
+    fn from_lane(lane: u128, bit_size: usize) -> Result<Self, DecompressError> {
+        let (negative, abs) = split_signed_lane(lane, bit_size);
+        let abs = Self::try_from(abs).map_err(|_| {
+            DecompressError::create(DecompressError::BinariesToIntErr(format!("Lane given: {}", lane)))
+        })?;
+        if negative {
+            Ok(-abs)
+        } else {
+            Ok(abs)
+        }
+    }
 }
 
 impl Comprezable for i16 {
@@ -365,10 +523,15 @@ impl Comprezable for i16 {
     }
 
     fn max_binaries(max_num: Option<u128>) -> BinaryChunk {
+        let _field = record_field::<Self>(max_num);
         let max_num = max_num.unwrap() as Self;
         BinaryChunk::Single(find_mult_8_bit_size(max_num.abs(), 8, 0, 2) + 4)
     }
 
+    fn schema_hash(max_num: Option<u128>) -> u64 {
+        SchemaHasher::new("i16").bound(max_num).finish()
+    }
+
     fn decompress(_compressed: Compressed) -> Result<i16, DecompressError> {
         Err(DecompressError::create(DecompressError::PrimitiveDataErr(String::new())))
     }
@@ -405,6 +568,35 @@ impl Comprezable for i16 {
     
         Ok(res)
     }
+
+    fn packs_to_lanes() -> bool {
+        true
+    }
+
+    fn signed_lanes() -> bool {
+        true
+    }
+
+    fn to_lane(&self, max_num: Option<u128>) -> Result<u128, CompressError> {
+        let max_num = max_num.unwrap() as Self;
+        if self.abs() > max_num {
+            return Err(CompressError::create(CompressError::Overflow(format!("given: {}, max-num: {}", self, max_num))))
+        }
+        let bit_size = find_mult_8_bit_size(max_num.abs(), 8, 0, 2) + 4;
+        Ok(signed_lane(*self < 0, self.unsigned_abs() as u128, bit_size))
+    }
+
+    fn from_lane(lane: u128, bit_size: usize) -> Result<Self, DecompressError> {
+        let (negative, abs) = split_signed_lane(lane, bit_size);
+        let abs = Self::try_from(abs).map_err(|_| {
+            DecompressError::create(DecompressError::BinariesToIntErr(format!("Lane given: {}", lane)))
+        })?;
+        if negative {
+            Ok(-abs)
+        } else {
+            Ok(abs)
+        }
+    }
 }
 
 
@@ -434,10 +626,15 @@ impl Comprezable for i32 {
     }
 
     fn max_binaries(max_num: Option<u128>) -> BinaryChunk {
+        let _field = record_field::<Self>(max_num);
         let max_num = max_num.unwrap() as Self;
         BinaryChunk::Single(find_mult_8_bit_size(max_num.abs(), 8, 0, 2) + 4)
     }
 
+    fn schema_hash(max_num: Option<u128>) -> u64 {
+        SchemaHasher::new("i32").bound(max_num).finish()
+    }
+
     fn decompress(_compressed: Compressed) -> Result<i32, DecompressError> {
         Err(DecompressError::create(DecompressError::PrimitiveDataErr(String::new())))
     }
@@ -474,6 +671,35 @@ impl Comprezable for i32 {
     
         Ok(res)
     }
+
+    fn packs_to_lanes() -> bool {
+        true
+    }
+
+    fn signed_lanes() -> bool {
+        true
+    }
+
+    fn to_lane(&self, max_num: Option<u128>) -> Result<u128, CompressError> {
+        let max_num = max_num.unwrap() as Self;
+        if self.abs() > max_num {
+            return Err(CompressError::create(CompressError::Overflow(format!("given: {}, max-num: {}", self, max_num))))
+        }
+        let bit_size = find_mult_8_bit_size(max_num.abs(), 8, 0, 2) + 4;
+        Ok(signed_lane(*self < 0, self.unsigned_abs() as u128, bit_size))
+    }
+
+    fn from_lane(lane: u128, bit_size: usize) -> Result<Self, DecompressError> {
+        let (negative, abs) = split_signed_lane(lane, bit_size);
+        let abs = Self::try_from(abs).map_err(|_| {
+            DecompressError::create(DecompressError::BinariesToIntErr(format!("Lane given: {}", lane)))
+        })?;
+        if negative {
+            Ok(-abs)
+        } else {
+            Ok(abs)
+        }
+    }
 }
 
 impl Comprezable for i64 {
@@ -501,10 +727,15 @@ impl Comprezable for i64 {
     }
 
     fn max_binaries(max_num: Option<u128>) -> BinaryChunk {
+        let _field = record_field::<Self>(max_num);
         let max_num = max_num.unwrap() as Self;
         BinaryChunk::Single(find_mult_8_bit_size(max_num.abs(), 8, 0, 2) + 4)
     }
 
+    fn schema_hash(max_num: Option<u128>) -> u64 {
+        SchemaHasher::new("i64").bound(max_num).finish()
+    }
+
     fn decompress(_compressed: Compressed) -> Result<i64, DecompressError> {
         Err(DecompressError::create(DecompressError::PrimitiveDataErr(String::new())))
     }
@@ -541,6 +772,35 @@ impl Comprezable for i64 {
     
         Ok(res)
     }
+
+    fn packs_to_lanes() -> bool {
+        true
+    }
+
+    fn signed_lanes() -> bool {
+        true
+    }
+
+    fn to_lane(&self, max_num: Option<u128>) -> Result<u128, CompressError> {
+        let max_num = max_num.unwrap() as Self;
+        if self.abs() > max_num {
+            return Err(CompressError::create(CompressError::Overflow(format!("given: {}, max-num: {}", self, max_num))))
+        }
+        let bit_size = find_mult_8_bit_size(max_num.abs(), 8, 0, 2) + 4;
+        Ok(signed_lane(*self < 0, self.unsigned_abs() as u128, bit_size))
+    }
+
+    fn from_lane(lane: u128, bit_size: usize) -> Result<Self, DecompressError> {
+        let (negative, abs) = split_signed_lane(lane, bit_size);
+        let abs = Self::try_from(abs).map_err(|_| {
+            DecompressError::create(DecompressError::BinariesToIntErr(format!("Lane given: {}", lane)))
+        })?;
+        if negative {
+            Ok(-abs)
+        } else {
+            Ok(abs)
+        }
+    }
 }
 
 impl Comprezable for i128 {
@@ -569,10 +829,15 @@ impl Comprezable for i128 {
     }
 
     fn max_binaries(max_num: Option<u128>) -> BinaryChunk {
+        let _field = record_field::<Self>(max_num);
         let max_num = max_num.unwrap() as Self;
         BinaryChunk::Single(find_mult_8_bit_size(max_num.abs(), 8, 0, 2) + 4)
     }
 
+    fn schema_hash(max_num: Option<u128>) -> u64 {
+        SchemaHasher::new("i128").bound(max_num).finish()
+    }
+
     fn decompress(_compressed: Compressed) -> Result<i128, DecompressError> {
         Err(DecompressError::create(DecompressError::PrimitiveDataErr(String::new())))
     }
@@ -609,79 +874,116 @@ impl Comprezable for i128 {
     
         Ok(res)
     }
-}
 
+    fn packs_to_lanes() -> bool {
+        true
+    }
+
+    fn signed_lanes() -> bool {
+        true
+    }
+
+    fn to_lane(&self, max_num: Option<u128>) -> Result<u128, CompressError> {
+        let max_num = max_num.unwrap() as Self;
+        if self.abs() > max_num {
+            return Err(CompressError::create(CompressError::Overflow(format!("given: {}, max-num: {}", self, max_num))))
+        }
+        let bit_size = find_mult_8_bit_size(max_num.abs(), 8, 0, 2) + 4;
+        Ok(signed_lane(*self < 0, self.unsigned_abs(), bit_size))
+    }
+
+    fn from_lane(lane: u128, bit_size: usize) -> Result<Self, DecompressError> {
+        let (negative, abs) = split_signed_lane(lane, bit_size);
+        let abs = Self::try_from(abs).map_err(|_| {
+            DecompressError::create(DecompressError::BinariesToIntErr(format!("Lane given: {}", lane)))
+        })?;
+        if negative {
+            Ok(-abs)
+        } else {
+            Ok(abs)
+        }
+    }
+}
 
-use lz4_flex::{compress_prepend_size, decompress_size_prepended};
 
-///Credit to LZ4 flex library for this compression
+///Compressed with the thread's default codec (LZ4 unless changed), see `crate::codec`
+///
+///Stored raw instead when the codec would not make it smaller, or when it is compressed already (JPEG, gzip, zstd, ...).
 impl Comprezable for Vec<u8> {
     fn compress(self) -> Result<Compressed, CompressError> {
         Err(CompressError::create(CompressError::DataNoSupported(String::new())))
     }
 
     fn compress_to_binaries(self, _max_num: Option<u128>) -> Result<Compressed, CompressError> {
-        let compressed = Compressed::Bytes(compress_prepend_size(self.as_slice()));
-        let compressed_length = compressed.to_bytes().len();
-
-        let compressed_metalength = Compressed::Binaries(compress_metalength_v2(compressed_length));
-      
-        let res = compressed_metalength.combine(compressed);
-        Ok(res)
+        codec::compress_bytes(self, codec::default_codec())
     }
 
-    fn max_binaries(_max_num: Option<u128>) -> BinaryChunk {
+    fn max_binaries(max_num: Option<u128>) -> BinaryChunk {
+        let _field = record_field::<Self>(max_num);
         BinaryChunk::Delimeter
     }
 
+    fn schema_hash(_max_num: Option<u128>) -> u64 {
+        SchemaHasher::new("Vec<u8>").finish()
+    }
+
     fn decompress(_compressed: Compressed) -> Result<Self, DecompressError> where Self: Sized {
         Err(DecompressError::create(DecompressError::PrimitiveDataErr(String::new())))
     }
 
     fn decompress_from_binaries(compressed: &mut Vec<u8>, _bit_size: Option<usize>) -> Result<Self, DecompressError> where Self:Sized {
-        let mut chunk_metas: Vec<u8> = vec![];
-        loop {
-            if compressed.len() < 8 {
-                return Err(DecompressError::WrongBytesLength(format!("Not enough bytes to calculate the metalength of compressed vector")))
-            }
-            let delimeter = compressed.remove(0);
-            match delimeter {
-                0 => {
-                    chunk_metas.extend(compressed.drain(0 .. 7));
-                },
-                1 => {
-                    chunk_metas.extend(compressed.drain(0 .. 7));
-                    break
-                },
-                _ => {
-                    panic!()
-                }
-            };
-        }
-
-        let chunk_metas = chunk_metas
-        .into_iter()
-        .map(|bit| bit.to_string())
-        .collect::<String>();
-    
-    
-        //binaries to int AKA; size (in bytes) of the compressed vec
-        let meta = u128::from_str_radix(&chunk_metas, 2).unwrap();
-        let length = compressed.len() as u128;
-        if length < meta * 8 {
-            return Err(DecompressError::WrongBytesLength(format!("length of compressed bytes is shorter than meta-length of compressed vector, AKA meta-length out of bound")))
+        codec::decompress_bytes(compressed).map(|(bytes, _)| bytes)
+    }
+}
+
+///UTF-8 bytes led by their length, stored as is
+impl Comprezable for String {
+    fn compress(self) -> Result<Compressed, CompressError> {
+        Err(CompressError::create(CompressError::DataNoSupported(String::new())))
+    }
+
+    fn compress_to_binaries(self, _max_num: Option<u128>) -> Result<Compressed, CompressError> {
+        if session::interning() {
+            return Ok(session::compress_interned(self))
         }
-        
+        Ok(compress_str_literal(self))
+    }
+
+    fn max_binaries(max_num: Option<u128>) -> BinaryChunk {
+        let _field = record_field::<Self>(max_num);
+        BinaryChunk::Delimeter
+    }
+
+    fn schema_hash(_max_num: Option<u128>) -> u64 {
+        SchemaHasher::new("String").finish()
+    }
 
-        let mut res_binaries: Vec<u8> = vec![];
-        for _ in 1 ..= meta {
-            res_binaries.extend(compressed.drain(0 .. 8));
+    fn decompress(_compressed: Compressed) -> Result<Self, DecompressError> where Self: Sized {
+        Err(DecompressError::create(DecompressError::PrimitiveDataErr(String::new())))
+    }
+
+    fn decompress_from_binaries(compressed: &mut Vec<u8>, _bit_size: Option<usize>) -> Result<Self, DecompressError> where Self:Sized {
+        if session::interning() {
+            return session::decompress_interned(compressed)
         }
+        decompress_str_literal(compressed)
+    }
+}
+
+pub(crate) fn compress_str_literal(string: String) -> Compressed {
+    let compressed_metalength = Compressed::Binaries(compress_varint(string.len() as u128));
+    compressed_metalength.combine(Compressed::Bytes(string.into_bytes()))
+}
 
-        //decode here
-        let res_binaries = Compressed::Binaries(res_binaries).to_bytes();
-        Ok(decompress_size_prepended(res_binaries.as_slice()).unwrap())
+pub(crate) fn decompress_str_literal(compressed: &mut Vec<u8>) -> Result<String, DecompressError> {
+    let len = calc_delimeter_size(compressed, 7)?;
+    if len > (compressed.len() / 8) as u128 {
+        return Err(DecompressError::create(DecompressError::WrongBytesLength(format!("string of {} bytes is out of bound", len))))
     }
+    let bytes = Compressed::Binaries(compressed.drain( .. len as usize * 8).collect()).to_bytes();
+    String::from_utf8(bytes).map_err(|err| {
+        DecompressError::create(DecompressError::FromBytesErr(err.to_string()))
+    })
 }
 
 use std::fmt::Debug;
@@ -691,22 +993,254 @@ impl<T: Comprezable + Clone + Debug> Comprezable for Vec<T> {
     }
 
     fn compress_to_binaries(self, max_num: Option<u128>) -> Result<Compressed, CompressError> {
-        let mut all_compressed = Compressed::new();
- 
-        for element in self {
-            let compressed = element.compress_to_binaries(max_num)?;
-            all_compressed = all_compressed.combine(compressed);
+        let count = Compressed::Binaries(compress_varint(self.len() as u128));
+        let elements = compress_elements(self, max_num)?;
+        Ok(count.combine(elements))
+    }
+
+    fn max_binaries(max_num: Option<u128>) -> BinaryChunk {
+        let _field = record_field::<Self>(max_num);
+        nested_max_binaries::<T>(max_num)
+    }
+
+    fn schema_hash(max_num: Option<u128>) -> u64 {
+        SchemaHasher::new("Vec").field::<T>(max_num).finish()
+    }
+
+    fn decompress(_compressed: Compressed) -> Result<Self, DecompressError> where Self: Sized {
+        Err(DecompressError::create(DecompressError::PrimitiveDataErr(String::new())))
+    }
+
+    fn decompress_from_binaries(compressed: &mut Vec<u8>, bit_size: Option<usize>) -> Result<Self, DecompressError> where Self:Sized {
+        let count = calc_delimeter_size(compressed, 7)?;
+        nested_decode(|| decompress_elements(compressed, count, bit_size))
+    }
+}
+
+///Vec with at most `MAX_LEN` elements, the element count is stored in a fixed width instead of a metalength.
+///## Example
+///```rust,ignore
+///#[derive(Comprezable, Debug)]
+///struct MyStruct {
+///    #[maxNum=1000]
+///    readings: BoundedVec<u16, 16>, //count takes 5 bits
+///}
+///```
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub struct BoundedVec<T, const MAX_LEN: usize>(pub Vec<T>);
+
+impl<T: Comprezable + Clone + Debug, const MAX_LEN: usize> Comprezable for BoundedVec<T, MAX_LEN> {
+    fn compress(self) -> Result<Compressed, CompressError> {
+        Err(CompressError::create(CompressError::DataNoSupported(String::new())))
+    }
+
+    fn compress_to_binaries(self, max_num: Option<u128>) -> Result<Compressed, CompressError> {
+        if self.0.len() > MAX_LEN {
+            return Err(CompressError::create(CompressError::Overflow(format!("given length: {}, max-len: {}", self.0.len(), MAX_LEN))))
+        }
+        let mut count = vec![];
+        push_bits(&mut count, self.0.len() as u128, bit_width(MAX_LEN as u128));
+        let elements = compress_elements(self.0, max_num)?;
+        Ok(Compressed::Binaries(count).combine(elements))
+    }
+
+    fn max_binaries(max_num: Option<u128>) -> BinaryChunk {
+        let _field = record_field::<Self>(max_num);
+        nested_max_binaries::<T>(max_num)
+    }
+
+    fn schema_hash(max_num: Option<u128>) -> u64 {
+        SchemaHasher::new("BoundedVec").write_u64(MAX_LEN as u64).field::<T>(max_num).finish()
+    }
+
+    fn decompress(_compressed: Compressed) -> Result<Self, DecompressError> where Self: Sized {
+        Err(DecompressError::create(DecompressError::PrimitiveDataErr(String::new())))
+    }
+
+    fn decompress_from_binaries(compressed: &mut Vec<u8>, bit_size: Option<usize>) -> Result<Self, DecompressError> where Self:Sized {
+        let count = take_bits(compressed, bit_width(MAX_LEN as u128))?;
+        if count > MAX_LEN as u128 {
+            return Err(DecompressError::create(DecompressError::WrongBytesLength(format!("element count {} is over max-len {}", count, MAX_LEN))))
+        }
+        nested_decode(|| decompress_elements(compressed, count, bit_size)).map(BoundedVec)
+    }
+}
+
+//elements back to back, no padding in between
+pub(crate) fn compress_elements<T: Comprezable>(elements: Vec<T>, max_num: Option<u128>) -> Result<Compressed, CompressError> {
+    if T::packs_to_lanes() {
+        if let BinaryChunk::Single(bit_size) = T::max_binaries(max_num) {
+            return pack_lanes(&elements, max_num, bit_size).map(Compressed::Binaries)
+        }
+    }
+
+    let mut res = vec![];
+    for element in elements {
+        element.compress_to_binaries(max_num)?.extend_to_res(&mut res);
+    }
+    Ok(Compressed::Binaries(res))
+}
+
+pub(crate) fn decompress_elements<T: Comprezable>(compressed: &mut Vec<u8>, count: u128, bit_size: Option<usize>) -> Result<Vec<T>, DecompressError> {
+    if let (true, Some(bit_size)) = (T::packs_to_lanes(), bit_size) {
+        return unpack_lanes(compressed, count, bit_size)
+    }
+
+    let mut res: Vec<T> = vec![];
+    for _ in 0 .. count {
+        res.push(T::decompress_from_binaries(compressed, bit_size)?);
+    }
+    Ok(res)
+}
+
+//fixed width integers packed a block at a time, bit for bit what compress_to_binaries gives
+pub(crate) fn pack_lanes<T: Comprezable>(elements: &[T], max_num: Option<u128>, bit_size: usize) -> Result<Vec<u8>, CompressError> {
+    let mut res = Vec::with_capacity(elements.len() * bit_size);
+    let mut lanes = Vec::with_capacity(LANE_BLOCK);
+    for block in elements.chunks(LANE_BLOCK) {
+        lanes.clear();
+        for element in block {
+            lanes.push(element.to_lane(max_num)?);
+        }
+        pack_block(&lanes, bit_size, &mut res);
+    }
+    Ok(res)
+}
+
+pub(crate) fn unpack_lanes<T: Comprezable>(compressed: &mut Vec<u8>, count: u128, bit_size: usize) -> Result<Vec<T>, DecompressError> {
+    let total = count.saturating_mul(bit_size as u128);
+    if (compressed.len() as u128) < total {
+        return Err(DecompressError::create(DecompressError::WrongBytesLength(String::from("Not enough bytes"))))
+    }
+    let total = total as usize;
+
+    let mut res = Vec::with_capacity(count as usize);
+    let mut cursor = 0;
+    let mut remaining = count as usize;
+    while remaining > 0 {
+        let block_len = remaining.min(LANE_BLOCK);
+        for lane in unpack_block(&compressed[cursor ..], block_len, bit_size) {
+            res.push(T::from_lane(lane, bit_size)?);
+        }
+        cursor += block_len * bit_size;
+        remaining -= block_len;
+    }
+    compressed.drain( .. total);
+    Ok(res)
+}
+
+const LANE_BLOCK: usize = 128;
+
+//every byte value as its 8 bits, most significant first
+const SPREAD: [[u8; 8]; 256] = {
+    let mut table = [[0; 8]; 256];
+    let mut byte = 0;
+    while byte < 256 {
+        let mut i = 0;
+        while i < 8 {
+            table[byte][i] = ((byte >> (7 - i)) & 1) as u8;
+            i += 1;
+        }
+        byte += 1;
+    }
+    table
+};
+
+//multiplying 8 bytes of 0 or 1 by this gathers them into the top byte, the first one highest
+const GATHER: u64 = 0x0102040810204080;
+
+//lanes of bit_size bits shifted into 64 bit words, then spread out 8 bits at a time
+pub(crate) fn pack_block(lanes: &[u128], bit_size: usize, res: &mut Vec<u8>) {
+    let total = lanes.len() * bit_size;
+    let mut words: Vec<u64> = Vec::with_capacity(total.div_ceil(64));
+    let mut acc: u128 = 0;
+    let mut acc_bits = 0;
+    for &lane in lanes {
+        //at most 64 bits at a time, so the accumulator never holds more than 127
+        let mut left = bit_size;
+        while left > 0 {
+            let take = left.min(64);
+            left -= take;
+            let part = (lane >> left) & ((1u128 << take) - 1);
+            acc = (acc << take) | part;
+            acc_bits += take;
+            if acc_bits >= 64 {
+                acc_bits -= 64;
+                words.push((acc >> acc_bits) as u64);
+                acc &= (1u128 << acc_bits) - 1;
+            }
+        }
+    }
+    if acc_bits > 0 {
+        words.push((acc << (64 - acc_bits)) as u64);
+    }
+
+    let start = res.len();
+    res.resize(start + words.len() * 64, 0);
+    for (word, bits) in words.iter().zip(res[start ..].chunks_exact_mut(64)) {
+        for (byte, bits) in word.to_be_bytes().iter().zip(bits.chunks_exact_mut(8)) {
+            bits.copy_from_slice(&SPREAD[*byte as usize]);
         }
-        let all_compressed = Compressed::Bytes(all_compressed.to_bytes());
+    }
+    res.truncate(start + total);
+}
+
+//count lanes of bit_size bits from the front of bits, which must hold count * bit_size of them
+pub(crate) fn unpack_block(bits: &[u8], count: usize, bit_size: usize) -> Vec<u128> {
+    let total = count * bit_size;
+    //one spare word so a lane never reads past the end
+    let mut words = vec![0u64; total.div_ceil(64) + 1];
+    for (word, chunk) in words.iter_mut().zip(bits[.. total].chunks(64)) {
+        let mut bytes = [0; 64];
+        bytes[.. chunk.len()].copy_from_slice(chunk);
+        *word = bytes.chunks_exact(8).fold(0, |word, group| (word << 8) | (u64::from_be_bytes(group.try_into().unwrap()).wrapping_mul(GATHER) >> 56));
+    }
+
+    //1 to 64 bits starting at position
+    let read = |position: usize, take: usize| {
+        let (index, offset) = (position / 64, position % 64);
+        let part = (words[index] << offset) >> (64 - take);
+        if offset + take > 64 {
+            part | (words[index + 1] >> (128 - offset - take))
+        } else {
+            part
+        }
+    };
+    (0 .. count).map(|i| {
+        let position = i * bit_size;
+        match bit_size {
+            0 => 0,
+            1 ..= 64 => read(position, bit_size) as u128,
+            _ => ((read(position, bit_size - 64) as u128) << 64) | read(position + bit_size - 64, 64) as u128,
+        }
+    }).collect()
+}
+
+///Vec whose elements are packed into a byte aligned blob led by its size in bytes.
+///Costs up to 7 bits of padding, but the blob can be skipped or handed over as whole bytes.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub struct AlignedVec<T>(pub Vec<T>);
+
+impl<T: Comprezable + Clone + Debug> Comprezable for AlignedVec<T> {
+    fn compress(self) -> Result<Compressed, CompressError> {
+        Err(CompressError::create(CompressError::DataNoSupported(String::new())))
+    }
+
+    fn compress_to_binaries(self, max_num: Option<u128>) -> Result<Compressed, CompressError> {
+        let count = Compressed::Binaries(compress_varint(self.0.len() as u128));
+        let all_compressed = Compressed::Bytes(compress_elements(self.0, max_num)?.to_bytes());
         let len = all_compressed.to_bytes().len();
-        let compressed_metalength = Compressed::Binaries(compress_metalength_v2(len));
-        let res = compressed_metalength.combine(all_compressed);
-        Ok(res)
+        let compressed_metalength = Compressed::Binaries(compress_varint(len as u128));
+        Ok(count.combine(compressed_metalength).combine(all_compressed))
     }
 
     fn max_binaries(max_num: Option<u128>) -> BinaryChunk {
-        let binary_chunk = T::max_binaries(max_num);
-        binary_chunk
+        let _field = record_field::<Self>(max_num);
+        nested_max_binaries::<T>(max_num)
+    }
+
+    fn schema_hash(max_num: Option<u128>) -> u64 {
+        SchemaHasher::new("AlignedVec").field::<T>(max_num).finish()
     }
 
     fn decompress(_compressed: Compressed) -> Result<Self, DecompressError> where Self: Sized {
@@ -714,31 +1248,214 @@ impl<T: Comprezable + Clone + Debug> Comprezable for Vec<T> {
     }
 
     fn decompress_from_binaries(compressed: &mut Vec<u8>, bit_size: Option<usize>) -> Result<Self, DecompressError> where Self:Sized {
+        let count = calc_delimeter_size(compressed, 7)?;
         let size = calc_delimeter_size(compressed, 7)?;
+        if size > (compressed.len() / 8) as u128 {
+            return Err(DecompressError::create(DecompressError::WrongBytesLength(String::from("length of compressed bytes is shorter than meta-length of compressed vector"))))
+        }
+        let mut res_binaries = compressed.drain( .. size as usize * 8).collect::<Vec<u8>>();
 
-        let mut res_binaries: Vec<u8> = vec![];
-        for _ in 1 ..= size {
-            res_binaries.extend(compressed.drain(0 .. 8));
-        }
-        //decode here
-        let mut res: Vec<T> = vec![];
-        loop {
-            match T::decompress_from_binaries(&mut res_binaries, bit_size) {
-                Ok(t) => {
-                    res.push(t);
-                },
-                Err(_) => {
-                    break
-                }
-            }
+        //exactly count elements, the zero padding at the end is never read
+        nested_decode(|| decompress_elements(&mut res_binaries, count, bit_size)).map(AlignedVec)
+    }
+}
+
+
+use std::{cell::{Cell, RefCell}, rc::Rc, sync::Arc};
+
+///Default limit of nested containers (Box/Rc/Arc, Vec and the other collections) a single decompress call may go through
+pub const DEFAULT_MAX_DECODE_DEPTH: usize = 128;
+
+///Default limit of elements an expanding encoding (`Rle`, `Sparse`, `Grid`, `Dict`) may decode to
+pub const DEFAULT_MAX_DECODE_LEN: usize = 1 << 24;
+
+thread_local! {
+    static EXPANDING: RefCell<Vec<&'static str>> = const { RefCell::new(Vec::new()) };
+    static DECODE_DEPTH: Cell<usize> = const { Cell::new(0) };
+    static MAX_DECODE_DEPTH: Cell<usize> = const { Cell::new(DEFAULT_MAX_DECODE_DEPTH) };
+    static MAX_DECODE_LEN: Cell<usize> = const { Cell::new(DEFAULT_MAX_DECODE_LEN) };
+}
+
+///Set how deep containers may nest while decompressing on the current thread.
+///Input nesting deeper than this returns `DecompressError::DepthLimitExceeded` instead of overflowing the stack.
+pub fn set_max_decode_depth(depth: usize) {
+    MAX_DECODE_DEPTH.with(|max| max.set(depth));
+}
+
+pub fn max_decode_depth() -> usize {
+    MAX_DECODE_DEPTH.with(|max| max.get())
+}
+
+///Set how many elements a run-length, sparse, grid or dictionary encoded collection, how many bits a session
+///delta, or how many bytes a codec's output may expand to while decompressing on the current thread. Those encodings can describe a huge collection in a few bits,
+///input asking for more returns `DecompressError::LengthLimitExceeded` instead of allocating it.
+pub fn set_max_decode_len(len: usize) {
+    MAX_DECODE_LEN.with(|max| max.set(len));
+}
+
+pub fn max_decode_len() -> usize {
+    MAX_DECODE_LEN.with(|max| max.get())
+}
+
+//len as read from the input, checked against the limit before anything that size is allocated
+pub(crate) fn check_decode_len(len: u128) -> Result<usize, DecompressError> {
+    let max_len = max_decode_len();
+    if len > max_len as u128 {
+        return Err(DecompressError::create(DecompressError::LengthLimitExceeded(format!("length: {}, limit: {}", len, max_len))))
+    }
+    Ok(len as usize)
+}
+
+struct ExpandGuard;
+
+impl Drop for ExpandGuard {
+    fn drop(&mut self) {
+        EXPANDING.with(|expanding| expanding.borrow_mut().pop());
+    }
+}
+
+struct DepthGuard;
+
+impl Drop for DepthGuard {
+    fn drop(&mut self) {
+        DECODE_DEPTH.with(|depth| depth.set(depth.get() - 1));
+    }
+}
+
+//a recursive type reaches itself again through a pointer or a collection, stop there and let it decode self-delimited
+pub(crate) fn nested_max_binaries<T: Comprezable>(max_num: Option<u128>) -> BinaryChunk {
+    let name = std::any::type_name::<T>();
+    let recursing = EXPANDING.with(|expanding| expanding.borrow().contains(&name));
+    if recursing {
+        return BinaryChunk::Delimeter
+    }
+
+    EXPANDING.with(|expanding| expanding.borrow_mut().push(name));
+    let _guard = ExpandGuard;
+    T::max_binaries(max_num)
+}
+
+//one level deeper for the decoding in f, every container goes through here so hostile nesting hits the limit
+pub(crate) fn nested_decode<R>(f: impl FnOnce() -> Result<R, DecompressError>) -> Result<R, DecompressError> {
+    let depth = DECODE_DEPTH.with(|depth| depth.get()) + 1;
+    let max_depth = max_decode_depth();
+    if depth > max_depth {
+        return Err(DecompressError::create(DecompressError::DepthLimitExceeded(format!("limit: {}", max_depth))))
+    }
+
+    DECODE_DEPTH.with(|current| current.set(depth));
+    let _guard = DepthGuard;
+    f()
+}
+
+//encodes every candidate from the same shared-pointer and session string state and keeps the smallest, ties go to the earlier one.
+//With a table active the losers are rolled back and the winner encoded again, so the table only holds what was written
+pub(crate) fn encode_smallest<K: Copy>(candidates: &[K], encode: impl Fn(K) -> Result<Vec<u8>, CompressError>) -> Result<(K, Vec<u8>), CompressError> {
+    let stateful = shared::sharing() || session::interning();
+    let mut best: Option<(K, Vec<u8>)> = None;
+    for &candidate in candidates {
+        let binaries = if stateful { shared::rollback(|| session::rollback(|| encode(candidate)))? } else { encode(candidate)? };
+        match &best {
+            Some((_, best)) if best.len() <= binaries.len() => {},
+            _ => best = Some((candidate, binaries)),
         }
-        Ok(res)
+    }
+
+    let (candidate, binaries) = best.ok_or(CompressError::create(CompressError::EncodeErr(String::from("Compress Error; no candidate encoding"))))?;
+    if stateful {
+        return Ok((candidate, encode(candidate)?))
+    }
+    Ok((candidate, binaries))
+}
+
+fn pointee_decompress<T: Comprezable>(compressed: &mut Vec<u8>, bit_size: Option<usize>) -> Result<T, DecompressError> {
+    nested_decode(|| T::decompress_from_binaries(compressed, bit_size))
+}
+
+impl<T: Comprezable> Comprezable for Box<T> {
+    fn compress(self) -> Result<Compressed, CompressError> {
+        (*self).compress()
+    }
+
+    fn compress_to_binaries(self, max_num: Option<u128>) -> Result<Compressed, CompressError> {
+        (*self).compress_to_binaries(max_num)
+    }
+
+    fn max_binaries(max_num: Option<u128>) -> BinaryChunk {
+        nested_max_binaries::<T>(max_num)
+    }
+
+    fn decompress(compressed: Compressed) -> Result<Self, DecompressError> where Self: Sized {
+        T::decompress(compressed).map(Box::new)
+    }
+
+    fn decompress_from_binaries(compressed: &mut Vec<u8>, bit_size: Option<usize>) -> Result<Self, DecompressError> where Self:Sized {
+        pointee_decompress::<T>(compressed, bit_size).map(Box::new)
+    }
+}
+
+impl<T: Comprezable + Clone + 'static> Comprezable for Rc<T> {
+    fn compress(self) -> Result<Compressed, CompressError> {
+        Rc::try_unwrap(self).unwrap_or_else(|shared| (*shared).clone()).compress()
+    }
+
+    fn compress_to_binaries(self, max_num: Option<u128>) -> Result<Compressed, CompressError> {
+        let address = Rc::as_ptr(&self) as usize;
+        shared::compress_pointer(self, address, max_num, |pointer, max_num| {
+            Rc::try_unwrap(pointer).unwrap_or_else(|shared| (*shared).clone()).compress_to_binaries(max_num)
+        })
+    }
+
+    fn max_binaries(max_num: Option<u128>) -> BinaryChunk {
+        nested_max_binaries::<T>(max_num)
+    }
+
+    fn decompress(compressed: Compressed) -> Result<Self, DecompressError> where Self: Sized {
+        T::decompress(compressed).map(Rc::new)
+    }
+
+    fn decompress_from_binaries(compressed: &mut Vec<u8>, bit_size: Option<usize>) -> Result<Self, DecompressError> where Self:Sized {
+        shared::decompress_pointer(compressed, bit_size, |compressed, bit_size| {
+            pointee_decompress::<T>(compressed, bit_size).map(Rc::new)
+        })
+    }
+}
+
+impl<T: Comprezable + Clone + 'static> Comprezable for Arc<T> {
+    fn compress(self) -> Result<Compressed, CompressError> {
+        Arc::try_unwrap(self).unwrap_or_else(|shared| (*shared).clone()).compress()
+    }
+
+    fn compress_to_binaries(self, max_num: Option<u128>) -> Result<Compressed, CompressError> {
+        let address = Arc::as_ptr(&self) as usize;
+        shared::compress_pointer(self, address, max_num, |pointer, max_num| {
+            Arc::try_unwrap(pointer).unwrap_or_else(|shared| (*shared).clone()).compress_to_binaries(max_num)
+        })
+    }
+
+    fn max_binaries(max_num: Option<u128>) -> BinaryChunk {
+        nested_max_binaries::<T>(max_num)
+    }
+
+    fn decompress(compressed: Compressed) -> Result<Self, DecompressError> where Self: Sized {
+        T::decompress(compressed).map(Arc::new)
+    }
+
+    fn decompress_from_binaries(compressed: &mut Vec<u8>, bit_size: Option<usize>) -> Result<Self, DecompressError> where Self:Sized {
+        shared::decompress_pointer(compressed, bit_size, |compressed, bit_size| {
+            pointee_decompress::<T>(compressed, bit_size).map(Arc::new)
+        })
     }
 }
 
 pub fn calc_delimeter_size(compressed: &mut Vec<u8>, delimeter_size: usize) -> Result<u128, DecompressError> {
     let mut chunk_metas: Vec<u8> = vec![];
+    //more groups than a u128 holds can only come from hostile input
+    let max_groups = 128usize.div_ceil(delimeter_size.max(1));
     loop {
+        if chunk_metas.len() / delimeter_size.max(1) >= max_groups {
+            return Err(DecompressError::create(DecompressError::BinariesToIntErr(format!("varint longer than {} groups of {} bits", max_groups, delimeter_size))))
+        }
         if compressed.len() < delimeter_size + 1 {
             return Err(DecompressError::WrongBytesLength(format!("Not enough bytes to calculate the metalength of compressed vector")))
         }
@@ -764,13 +1481,29 @@ pub fn calc_delimeter_size(compressed: &mut Vec<u8>, delimeter_size: usize) -> R
 
 
     //binaries to int AKA; size (in bytes) of the compressed vec
-    let meta = u128::from_str_radix(&chunk_metas, 2).unwrap();
+    let meta = u128::from_str_radix(&chunk_metas, 2).map_err(|_| DecompressError::create(DecompressError::BinariesToIntErr(format!("varint of {} bits does not fit in u128", chunk_metas.len()))))?;
 
     Ok(meta)
 }
 
 
 pub fn comprez_enum_val<T: Comprezable + Clone + Debug>(val: Option<T>, n: usize, max_num: Option<u128>) -> Result<Compressed, CompressError> {
+    let meta = compress_enum_index(n);
+
+    match val {
+        Some(t) => {
+            let compressed = t.compress_to_binaries(max_num)?;
+            let compressed = meta.combine(compressed);
+            Ok(compressed)
+        },
+        None => {
+            Ok(meta)
+        }
+    }
+}
+
+//variant index in 4-bit groups, each led by a delimeter bit; read it back with `calc_delimeter_size(compressed, 4)`
+pub(crate) fn compress_enum_index(n: usize) -> Compressed {
     let n = format!("{:b}", n);
     let meta = n.chars().map(|c| c.to_digit(2).unwrap() as u8).rev().collect::<Vec<u8>>();
     let mut meta = meta.chunks(4).map(|chunk| {
@@ -788,18 +1521,7 @@ pub fn comprez_enum_val<T: Comprezable + Clone + Debug>(val: Option<T>, n: usize
     meta.reverse();
     meta.last_mut().unwrap()[0] = 1;
     let meta = meta.into_iter().flatten().collect::<Vec<u8>>();
-    let meta = Compressed::Binaries(meta);
-
-    match val {
-        Some(t) => {
-            let compressed = t.compress_to_binaries(max_num)?;
-            let compressed = meta.combine(compressed);
-            Ok(compressed)
-        },
-        None => {
-            Ok(meta)
-        }
-    }
+    Compressed::Binaries(meta)
 }
 
 
@@ -816,8 +1538,10 @@ fn compress_num<N: Ord + std::ops::Div<Output = N> + std::ops::Rem<Output = N> +
     mult_8_bit
 }
 
-fn compress_metalength_v2(num: usize) -> Vec<u8> {
-    let mut binaries = encode(num, 2, 0, 0).iter().map(|&bit| bit as u8).collect::<Vec<u8>>();
+///Encode an integer into 7-bit groups, each led by a delimeter bit (1 marks the last group).
+///Read it back with `calc_delimeter_size(compressed, 7)`
+pub fn compress_varint(num: u128) -> Vec<u8> {
+    let mut binaries = encode(num, 2, 1, 0).iter().map(|&bit| bit as u8).collect::<Vec<u8>>();
     binaries.reverse();
 
     let mut reversed_chunked_meta = binaries.chunks(7).map(|chunk| {
@@ -841,6 +1565,46 @@ fn compress_metalength_v2(num: usize) -> Vec<u8> {
 }
 
 
+//push num as bit_size binaries, most significant bit first
+pub(crate) fn push_bits(res: &mut Vec<u8>, num: u128, bit_size: usize) {
+    for i in (0 .. bit_size).rev() {
+        res.push(((num >> i) & 1) as u8);
+    }
+}
+
+pub(crate) fn take_bits(compressed: &mut Vec<u8>, bit_size: usize) -> Result<u128, DecompressError> {
+    if compressed.len() < bit_size {
+        return Err(DecompressError::create(DecompressError::WrongBytesLength(String::from("Not enough bytes"))))
+    }
+    Ok(compressed.drain( .. bit_size).fold(0, |acc, bit| (acc << 1) | bit as u128))
+}
+
+
+//like take_bits, but moves a cursor instead of draining so long runs of reads stay linear
+pub(crate) fn read_bits(compressed: &[u8], cursor: &mut usize, bit_size: usize) -> Result<u128, DecompressError> {
+    let bits = compressed.get(*cursor .. *cursor + bit_size).ok_or(DecompressError::create(DecompressError::WrongBytesLength(String::from("Not enough bytes"))))?;
+    *cursor += bit_size;
+    Ok(bits.iter().fold(0, |acc, &bit| (acc << 1) | bit as u128))
+}
+
+//sign bit (1 for positive) followed by the absolute value, like the signed compress_to_binaries
+pub(crate) fn signed_lane(negative: bool, abs: u128, bit_size: usize) -> u128 {
+    let sign = if negative { 0 } else { 1 };
+    (sign << (bit_size - 1)) | abs
+}
+
+pub(crate) fn split_signed_lane(lane: u128, bit_size: usize) -> (bool, u128) {
+    let negative = (lane >> (bit_size - 1)) & 1 == 0;
+    let abs = lane & ((1 << (bit_size - 1)) - 1);
+    (negative, abs)
+}
+
+//number of bits needed to hold num
+pub(crate) fn bit_width(num: u128) -> usize {
+    (128 - num.leading_zeros()) as usize
+}
+
+
 //turn integer to base 2, and then insert 0 to the front depends on the bit size
 fn encode<N: Ord + std::ops::Div<Output = N> + std::ops::Rem<Output = N> + Copy>(mut num: N, r: N, bit_size: usize, zero: N) -> Vec<N> {
     let mut res = vec![];
@@ -0,0 +1,217 @@
+//! Delta and delta-of-delta encoding for Vec of integers.
+//!
+//! The first value is stored as is, every following value as the zigzag encoded difference
+//! to the previous one. Differences are packed in blocks of 128, each block using only as
+//! many bits as its widest difference needs, so slowly moving sequences like timestamps
+//! or monotonic ids shrink to a few bits per element.
+//!
+//! ## Example
+//! ```rust,ignore
+//! use comprez::delta::{Delta, DeltaOfDelta};
+//!
+//! #[derive(Comprezable, Debug)]
+//! struct Samples {
+//!     ids: Delta<u32>,
+//!     timestamps: DeltaOfDelta<u64>,
+//! }
+//! ```
+
+use crate::{comprezable::{bit_width, calc_delimeter_size, compress_varint, push_bits, take_bits, Comprezable}, error::{CompressError, DecompressError}, schema::{record_field, SchemaHasher}, BinaryChunk, Compressed};
+
+const BLOCK_SIZE: usize = 128;
+const WIDTH_BITS: usize = 8;
+
+///Integers that can be delta encoded, the arithmetic wraps in 128 bits so any sequence is exact
+pub trait DeltaInt: Copy {
+    fn to_u128(self) -> u128;
+    fn from_u128(num: u128) -> Self;
+}
+
+impl DeltaInt for u8 {
+    fn to_u128(self) -> u128 { self as u128 }
+    fn from_u128(num: u128) -> Self { num as Self }
+}
+
+impl DeltaInt for u16 {
+    fn to_u128(self) -> u128 { self as u128 }
+    fn from_u128(num: u128) -> Self { num as Self }
+}
+
+impl DeltaInt for u32 {
+    fn to_u128(self) -> u128 { self as u128 }
+    fn from_u128(num: u128) -> Self { num as Self }
+}
+
+impl DeltaInt for u64 {
+    fn to_u128(self) -> u128 { self as u128 }
+    fn from_u128(num: u128) -> Self { num as Self }
+}
+
+impl DeltaInt for u128 {
+    fn to_u128(self) -> u128 { self }
+    fn from_u128(num: u128) -> Self { num }
+}
+
+impl DeltaInt for i8 {
+    fn to_u128(self) -> u128 { self as i128 as u128 }
+    fn from_u128(num: u128) -> Self { num as Self }
+}
+
+impl DeltaInt for i16 {
+    fn to_u128(self) -> u128 { self as i128 as u128 }
+    fn from_u128(num: u128) -> Self { num as Self }
+}
+
+impl DeltaInt for i32 {
+    fn to_u128(self) -> u128 { self as i128 as u128 }
+    fn from_u128(num: u128) -> Self { num as Self }
+}
+
+impl DeltaInt for i64 {
+    fn to_u128(self) -> u128 { self as i128 as u128 }
+    fn from_u128(num: u128) -> Self { num as Self }
+}
+
+impl DeltaInt for i128 {
+    fn to_u128(self) -> u128 { self as u128 }
+    fn from_u128(num: u128) -> Self { num as Self }
+}
+
+///Vec of integers stored as the first value followed by zigzag deltas
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub struct Delta<T>(pub Vec<T>);
+
+///Vec of integers stored as the first value, the first delta, and then the change between deltas.
+///Best for sequences growing at a steady rate, like sampled timestamps.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub struct DeltaOfDelta<T>(pub Vec<T>);
+
+impl<T: DeltaInt> Comprezable for Delta<T> {
+    fn compress(self) -> Result<Compressed, CompressError> {
+        Err(CompressError::create(CompressError::DataNoSupported(String::new())))
+    }
+
+    fn compress_to_binaries(self, _max_num: Option<u128>) -> Result<Compressed, CompressError> {
+        Ok(compress_delta(self.0, 1))
+    }
+
+    fn max_binaries(max_num: Option<u128>) -> BinaryChunk {
+        let _field = record_field::<Self>(max_num);
+        BinaryChunk::Delimeter
+    }
+
+    fn schema_hash(_max_num: Option<u128>) -> u64 {
+        SchemaHasher::new("Delta").write(std::any::type_name::<T>().as_bytes()).finish()
+    }
+
+    fn decompress(_compressed: Compressed) -> Result<Self, DecompressError> where Self: Sized {
+        Err(DecompressError::create(DecompressError::PrimitiveDataErr(String::new())))
+    }
+
+    fn decompress_from_binaries(compressed: &mut Vec<u8>, _bit_size: Option<usize>) -> Result<Self, DecompressError> where Self:Sized {
+        decompress_delta(compressed, 1).map(Delta)
+    }
+}
+
+impl<T: DeltaInt> Comprezable for DeltaOfDelta<T> {
+    fn compress(self) -> Result<Compressed, CompressError> {
+        Err(CompressError::create(CompressError::DataNoSupported(String::new())))
+    }
+
+    fn compress_to_binaries(self, _max_num: Option<u128>) -> Result<Compressed, CompressError> {
+        Ok(compress_delta(self.0, 2))
+    }
+
+    fn max_binaries(max_num: Option<u128>) -> BinaryChunk {
+        let _field = record_field::<Self>(max_num);
+        BinaryChunk::Delimeter
+    }
+
+    fn schema_hash(_max_num: Option<u128>) -> u64 {
+        SchemaHasher::new("DeltaOfDelta").write(std::any::type_name::<T>().as_bytes()).finish()
+    }
+
+    fn decompress(_compressed: Compressed) -> Result<Self, DecompressError> where Self: Sized {
+        Err(DecompressError::create(DecompressError::PrimitiveDataErr(String::new())))
+    }
+
+    fn decompress_from_binaries(compressed: &mut Vec<u8>, _bit_size: Option<usize>) -> Result<Self, DecompressError> where Self:Sized {
+        decompress_delta(compressed, 2).map(DeltaOfDelta)
+    }
+}
+
+fn zigzag(num: u128) -> u128 {
+    let num = num as i128;
+    ((num << 1) ^ (num >> 127)) as u128
+}
+
+fn unzigzag(num: u128) -> u128 {
+    ((num >> 1) as i128 ^ -((num & 1) as i128)) as u128
+}
+
+//count, one head per order, then the remaining differences in blocks
+fn compress_delta<T: DeltaInt>(values: Vec<T>, order: usize) -> Compressed {
+    let mut res = compress_varint(values.len() as u128);
+    let mut seq = values.into_iter().map(|value| value.to_u128()).collect::<Vec<u128>>();
+
+    for _ in 0 .. order {
+        if seq.is_empty() {
+            break
+        }
+        res.extend(compress_varint(zigzag(seq[0])));
+        seq = seq.windows(2).map(|pair| pair[1].wrapping_sub(pair[0])).collect();
+    }
+
+    for block in seq.chunks(BLOCK_SIZE) {
+        let zigzagged = block.iter().map(|&delta| zigzag(delta)).collect::<Vec<u128>>();
+        let width = zigzagged.iter().map(|&delta| bit_width(delta)).max().unwrap_or(0);
+        push_bits(&mut res, width as u128, WIDTH_BITS);
+        for delta in zigzagged {
+            push_bits(&mut res, delta, width);
+        }
+    }
+
+    Compressed::Binaries(res)
+}
+
+fn decompress_delta<T: DeltaInt>(compressed: &mut Vec<u8>, order: usize) -> Result<Vec<T>, DecompressError> {
+    let count = calc_delimeter_size(compressed, 7)?;
+    let heads_len = (order as u128).min(count) as usize;
+
+    let mut heads = vec![];
+    for _ in 0 .. heads_len {
+        heads.push(unzigzag(calc_delimeter_size(compressed, 7)?));
+    }
+
+    let mut remaining = count - heads_len as u128;
+    let mut seq = vec![];
+    while remaining > 0 {
+        let width = take_bits(compressed, WIDTH_BITS)? as usize;
+        if width > 128 {
+            return Err(DecompressError::create(DecompressError::BinariesToIntErr(format!("delta width: {}", width))))
+        }
+        let block_len = remaining.min(BLOCK_SIZE as u128);
+        for _ in 0 .. block_len {
+            seq.push(unzigzag(take_bits(compressed, width)?));
+        }
+        remaining -= block_len;
+    }
+
+    for head in heads.into_iter().rev() {
+        let mut prev = head;
+        let mut level = vec![head];
+        for delta in seq {
+            prev = prev.wrapping_add(delta);
+            level.push(prev);
+        }
+        seq = level;
+    }
+
+    seq.into_iter().map(|num| {
+        let value = T::from_u128(num);
+        if value.to_u128() != num {
+            return Err(DecompressError::create(DecompressError::BinariesToIntErr(format!("{} does not fit the integer type", num))))
+        }
+        Ok(value)
+    }).collect()
+}
@@ -0,0 +1,87 @@
+//! Dictionary encoding for low-cardinality Vec.
+//!
+//! The distinct values are written once, then every element as an index into them of
+//! `ceil(log2(distinct values))` bits. Works for anything Comprezable and comparable:
+//! enums, strings, integers, whole structs.
+//!
+//! ## Example
+//! ```rust,ignore
+//! use comprez::dictionary::Dict;
+//!
+//! #[derive(Comprezable, Debug)]
+//! struct Orders {
+//!     statuses: Dict<Status>,
+//!     countries: Dict<String>,
+//! }
+//! ```
+
+use std::fmt::Debug;
+
+use crate::{comprezable::{bit_width, calc_delimeter_size, check_decode_len, compress_elements, compress_varint, decompress_elements, nested_decode, nested_max_binaries, push_bits, read_bits, Comprezable}, error::{CompressError, DecompressError}, schema::{record_field, SchemaHasher}, BinaryChunk, Compressed};
+
+///Vec stored as its distinct values followed by one index per element
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub struct Dict<T>(pub Vec<T>);
+
+impl<T: Comprezable + PartialEq + Clone + Debug> Comprezable for Dict<T> {
+    fn compress(self) -> Result<Compressed, CompressError> {
+        Err(CompressError::create(CompressError::DataNoSupported(String::new())))
+    }
+
+    //distinct count, distinct values, element count, indexes
+    fn compress_to_binaries(self, max_num: Option<u128>) -> Result<Compressed, CompressError> {
+        let mut dictionary: Vec<T> = vec![];
+        let mut indexes = vec![];
+        for element in self.0.iter() {
+            let index = match dictionary.iter().position(|entry| entry == element) {
+                Some(index) => index,
+                None => {
+                    dictionary.push(element.clone());
+                    dictionary.len() - 1
+                }
+            };
+            indexes.push(index);
+        }
+
+        let mut res = compress_varint(dictionary.len() as u128);
+        let index_size = bit_width(dictionary.len().saturating_sub(1) as u128);
+        compress_elements(dictionary, max_num)?.extend_to_res(&mut res);
+
+        res.extend(compress_varint(indexes.len() as u128));
+        for index in indexes {
+            push_bits(&mut res, index as u128, index_size);
+        }
+        Ok(Compressed::Binaries(res))
+    }
+
+    fn max_binaries(max_num: Option<u128>) -> BinaryChunk {
+        let _field = record_field::<Self>(max_num);
+        nested_max_binaries::<T>(max_num)
+    }
+
+    fn schema_hash(max_num: Option<u128>) -> u64 {
+        SchemaHasher::new("Dict").field::<T>(max_num).finish()
+    }
+
+    fn decompress(_compressed: Compressed) -> Result<Self, DecompressError> where Self: Sized {
+        Err(DecompressError::create(DecompressError::PrimitiveDataErr(String::new())))
+    }
+
+    fn decompress_from_binaries(compressed: &mut Vec<u8>, bit_size: Option<usize>) -> Result<Self, DecompressError> where Self:Sized {
+        let dictionary_len = calc_delimeter_size(compressed, 7)?;
+        let dictionary: Vec<T> = nested_decode(|| decompress_elements(compressed, dictionary_len, bit_size))?;
+        let index_size = bit_width(dictionary.len().saturating_sub(1) as u128);
+
+        //a one entry dictionary has 0 bit indexes, the count alone decides how many elements come out
+        let count = check_decode_len(calc_delimeter_size(compressed, 7)?)?;
+        let mut cursor = 0;
+        let mut res = vec![];
+        for _ in 0 .. count {
+            let index = read_bits(compressed, &mut cursor, index_size)? as usize;
+            let entry = dictionary.get(index).ok_or(DecompressError::create(DecompressError::BinariesToIntErr(format!("dictionary index: {}, size: {}", index, dictionary.len()))))?;
+            res.push(entry.clone());
+        }
+        compressed.drain( .. cursor);
+        Ok(Dict(res))
+    }
+}
@@ -0,0 +1,221 @@
+//! Diffs between an old and a new value of the same type, for state synchronisation.
+//!
+//! [`compress_diff`] writes only what changed from `old` to `new`, and [`apply_patch`] turns the
+//! old value into the new one. Structs write one changed bit per field and then the changed fields
+//! only, recursing into nested structs and `Vec`; integers, strings and byte vectors are written whole
+//! when they changed.
+//!
+//! `Diffable` ships for the integers, `String`, `Vec<u8>`, `Box` and `Vec`; it has no derive. A struct
+//! implements it by hand, writing its fields through [`FieldDiffs`] and reading them back through
+//! [`FieldPatches`], which takes each field's layout from the struct's own `max_binaries`, the same
+//! way `decompress` does. Types with no parts to diff use [`diff_whole`] and [`patch_whole`]:
+//!
+//! ## Example
+//! ```rust,ignore
+//! use comprez::diff::{apply_patch, compress_diff, Diffable, FieldDiffs, FieldPatches};
+//!
+//! #[derive(Comprezable, Debug, Clone, PartialEq)]
+//! struct Player {
+//!     #[maxNum=1000]
+//!     hp: u16,
+//!     name: String,
+//!     #[maxNum=100]
+//!     inventory: Vec<u8>,
+//! }
+//!
+//! impl Diffable for Player {
+//!     fn diff_to_binaries(&self, old: &Self, _max_num: Option<u128>) -> Result<Compressed, CompressError> {
+//!         let mut fields = FieldDiffs::new();
+//!         fields.push(&self.hp, &old.hp, Some(1000))?;
+//!         fields.push(&self.name, &old.name, None)?;
+//!         fields.push(&self.inventory, &old.inventory, Some(100))?;
+//!         Ok(fields.finish())
+//!     }
+//!
+//!     fn patch_from_binaries(&mut self, compressed: &mut Vec<u8>, _chunk: &BinaryChunk) -> Result<(), DecompressError> {
+//!         let mut fields = FieldPatches::read::<Self>(compressed)?;
+//!         fields.apply(&mut self.hp, compressed)?;
+//!         fields.apply(&mut self.name, compressed)?;
+//!         fields.apply(&mut self.inventory, compressed)
+//!     }
+//! }
+//!
+//! let patch = compress_diff(&old, &new)?;
+//! apply_patch(&mut old, patch)?;
+//! ```
+
+use std::fmt::Debug;
+
+use crate::{comprezable::{calc_delimeter_size, compress_elements, compress_varint, decompress_elements, Comprezable}, error::{CompressError, DecompressError}, BinaryChunk, Compressed};
+
+///A value that can be sent as its changes from an older value
+pub trait Diffable: Comprezable + PartialEq + Sized {
+    ///Writes what changed from `old` to `self`, `max_num` as in `compress_to_binaries`
+    fn diff_to_binaries(&self, old: &Self, max_num: Option<u128>) -> Result<Compressed, CompressError>;
+
+    ///Applies what `diff_to_binaries` wrote, `chunk` is this value's `max_binaries`
+    fn patch_from_binaries(&mut self, compressed: &mut Vec<u8>, chunk: &BinaryChunk) -> Result<(), DecompressError>;
+}
+
+///The changes from `old` to `new`
+pub fn compress_diff<T: Diffable>(old: &T, new: &T) -> Result<Compressed, CompressError> {
+    new.diff_to_binaries(old, None)
+}
+
+///Turns `old` into the value the patch was made from
+pub fn apply_patch<T: Diffable>(old: &mut T, patch: Compressed) -> Result<(), DecompressError> {
+    let mut binaries = patch.to_binaries();
+    old.patch_from_binaries(&mut binaries, &T::max_binaries(None))
+}
+
+///Struct diff being written: one changed bit per field, then the changed fields
+#[derive(Debug, Default)]
+pub struct FieldDiffs {
+    mask: Vec<u8>,
+    fields: Vec<u8>,
+}
+
+impl FieldDiffs {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    ///The next field, `max_num` as its attribute gives it
+    pub fn push<T: Diffable>(&mut self, new: &T, old: &T, max_num: Option<u128>) -> Result<(), CompressError> {
+        if new == old {
+            self.mask.push(0);
+        } else {
+            self.mask.push(1);
+            new.diff_to_binaries(old, max_num)?.extend_to_res(&mut self.fields);
+        }
+        Ok(())
+    }
+
+    pub fn finish(self) -> Compressed {
+        Compressed::Binaries(self.mask).combine(Compressed::Binaries(self.fields))
+    }
+}
+
+///Struct diff being applied, field by field in the order they were pushed
+#[derive(Debug)]
+pub struct FieldPatches {
+    mask: Vec<u8>,
+    chunks: Vec<BinaryChunk>,
+    next: usize,
+}
+
+impl FieldPatches {
+    ///Reads the changed bits, one per field of `T::max_binaries`
+    pub fn read<T: Comprezable>(compressed: &mut Vec<u8>) -> Result<Self, DecompressError> {
+        let chunks = match T::max_binaries(None) {
+            BinaryChunk::Nested(chunks) => chunks,
+            chunk => vec![chunk],
+        };
+        if compressed.len() < chunks.len() {
+            return Err(DecompressError::create(DecompressError::WrongBytesLength(format!("Not enough bytes for {} changed fields", chunks.len()))))
+        }
+        let mask = compressed.drain( .. chunks.len()).collect();
+        Ok(FieldPatches { mask, chunks, next: 0 })
+    }
+
+    ///The next field, left as it is when it did not change
+    pub fn apply<T: Diffable>(&mut self, field: &mut T, compressed: &mut Vec<u8>) -> Result<(), DecompressError> {
+        let index = self.next;
+        self.next += 1;
+        match (self.mask.get(index), self.chunks.get(index)) {
+            (Some(1), Some(chunk)) => field.patch_from_binaries(compressed, chunk),
+            (Some(_), Some(_)) => Ok(()),
+            _ => Err(DecompressError::create(DecompressError::WrongBytesLength(format!("field {} of {}", index, self.chunks.len())))),
+        }
+    }
+}
+
+///`diff_to_binaries` for a type with no parts to diff (integers, strings, enums): the whole new value
+pub fn diff_whole<T: Comprezable + Clone>(new: &T, max_num: Option<u128>) -> Result<Compressed, CompressError> {
+    new.clone().compress_to_binaries(max_num)
+}
+
+///`patch_from_binaries` matching `diff_whole`
+pub fn patch_whole<T: Comprezable>(value: &mut T, compressed: &mut Vec<u8>, chunk: &BinaryChunk) -> Result<(), DecompressError> {
+    *value = chunk.decompress(compressed)?;
+    Ok(())
+}
+
+macro_rules! diff_whole {
+    ($($t:ty),*) => {
+        $(
+            impl Diffable for $t {
+                fn diff_to_binaries(&self, _old: &Self, max_num: Option<u128>) -> Result<Compressed, CompressError> {
+                    diff_whole(self, max_num)
+                }
+
+                fn patch_from_binaries(&mut self, compressed: &mut Vec<u8>, chunk: &BinaryChunk) -> Result<(), DecompressError> {
+                    patch_whole(self, compressed, chunk)
+                }
+            }
+        )*
+    };
+}
+
+diff_whole!(u16, u32, u64, u128, i8, i16, i32, i64, i128, String, Vec<u8>);
+
+impl<T: Diffable> Diffable for Box<T> {
+    fn diff_to_binaries(&self, old: &Self, max_num: Option<u128>) -> Result<Compressed, CompressError> {
+        (**self).diff_to_binaries(old, max_num)
+    }
+
+    fn patch_from_binaries(&mut self, compressed: &mut Vec<u8>, chunk: &BinaryChunk) -> Result<(), DecompressError> {
+        (**self).patch_from_binaries(compressed, chunk)
+    }
+}
+
+//new length, changed element count, the gap before each changed element, their diffs, then the appended elements
+impl<T: Diffable + Clone + Debug> Diffable for Vec<T> {
+    fn diff_to_binaries(&self, old: &Self, max_num: Option<u128>) -> Result<Compressed, CompressError> {
+        let common = self.len().min(old.len());
+        let changed = (0 .. common).filter(|&i| self[i] != old[i]).collect::<Vec<usize>>();
+
+        let mut res = compress_varint(self.len() as u128);
+        res.extend(compress_varint(changed.len() as u128));
+        let mut next = 0;
+        for &i in changed.iter() {
+            res.extend(compress_varint((i - next) as u128));
+            next = i + 1;
+        }
+        for i in changed {
+            self[i].diff_to_binaries(&old[i], max_num)?.extend_to_res(&mut res);
+        }
+        compress_elements(self[common ..].to_vec(), max_num)?.extend_to_res(&mut res);
+        Ok(Compressed::Binaries(res))
+    }
+
+    fn patch_from_binaries(&mut self, compressed: &mut Vec<u8>, chunk: &BinaryChunk) -> Result<(), DecompressError> {
+        let len = calc_delimeter_size(compressed, 7)? as usize;
+        let common = len.min(self.len());
+        let count = calc_delimeter_size(compressed, 7)?;
+
+        let mut changed = vec![];
+        let mut next: u128 = 0;
+        for _ in 0 .. count {
+            let i = next.checked_add(calc_delimeter_size(compressed, 7)?).ok_or(DecompressError::create(DecompressError::WrongBytesLength(String::from("changed element gap overflows u128"))))?;
+            if i >= common as u128 {
+                return Err(DecompressError::create(DecompressError::WrongBytesLength(format!("changed element {} of {}", i, common))))
+            }
+            changed.push(i as usize);
+            next = i + 1;
+        }
+
+        self.truncate(common);
+        for i in changed {
+            self[i].patch_from_binaries(compressed, chunk)?;
+        }
+
+        let bit_size = match chunk {
+            BinaryChunk::Single(size) => Some(*size),
+            _ => None,
+        };
+        self.extend(decompress_elements::<T>(compressed, (len - common) as u128, bit_size)?);
+        Ok(())
+    }
+}
+
@@ -0,0 +1,177 @@
+//! Dirty-field tracking, for sending only the fields written since the last flush.
+//!
+//! A diff (`crate::diff`) needs the old value kept next to the new one. [`Tracked`] records
+//! writes as they happen instead: every change goes through [`Tracked::modify`] with the index of
+//! the field it writes, and [`Tracked::compress_dirty`] then writes a field mask and those fields only.
+//! The receiver applies them to its copy with [`apply_dirty`].
+//!
+//! Field indexes follow declaration order, as in `max_binaries`. For whole entity tables,
+//! [`compress_dirty_rows`] and [`apply_dirty_rows`] do the same for only the rows that changed.
+//!
+//! ## Example
+//! ```rust,ignore
+//! use comprez::dirty::{apply_dirty, DirtyFields, Tracked};
+//!
+//! #[derive(Comprezable, Debug, Clone)]
+//! struct Entity {
+//!     #[maxNum=1000]
+//!     hp: u16,
+//!     name: String,
+//! }
+//!
+//! impl DirtyFields for Entity {
+//!     fn compress_field(&self, index: usize) -> Result<Compressed, CompressError> {
+//!         match index {
+//!             0 => self.hp.compress_to_binaries(Some(1000)),
+//!             _ => self.name.clone().compress_to_binaries(None),
+//!         }
+//!     }
+//!
+//!     fn decompress_field(&mut self, index: usize, compressed: &mut Vec<u8>, chunk: &BinaryChunk) -> Result<(), DecompressError> {
+//!         match index {
+//!             0 => self.hp = chunk.decompress(compressed)?,
+//!             _ => self.name = chunk.decompress(compressed)?,
+//!         }
+//!         Ok(())
+//!     }
+//! }
+//!
+//! let mut entity = Tracked::new(entity);
+//! entity.modify(0, |entity| entity.hp -= 10);
+//! let update = entity.compress_dirty()?;
+//! apply_dirty(&mut remote_entity, update)?;
+//! ```
+
+use std::ops::Deref;
+
+use crate::{comprezable::{calc_delimeter_size, compress_varint, Comprezable}, error::{CompressError, DecompressError}, BinaryChunk, Compressed};
+
+///A struct whose fields can be written and read one at a time
+pub trait DirtyFields: Comprezable + Sized {
+    ///Writes field `index` the way `compress_to_binaries` writes it inside the struct
+    fn compress_field(&self, index: usize) -> Result<Compressed, CompressError>;
+
+    ///Reads field `index` into `self`, `chunk` is that field's `max_binaries`
+    fn decompress_field(&mut self, index: usize, compressed: &mut Vec<u8>, chunk: &BinaryChunk) -> Result<(), DecompressError>;
+
+    ///One chunk per field, from `max_binaries`
+    fn field_chunks() -> Vec<BinaryChunk> {
+        match Self::max_binaries(None) {
+            BinaryChunk::Nested(chunks) => chunks,
+            chunk => vec![chunk],
+        }
+    }
+}
+
+///Value that remembers which of its fields were written since the last `compress_dirty`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tracked<T> {
+    value: T,
+    dirty: Vec<bool>,
+}
+
+impl<T: DirtyFields> Tracked<T> {
+    ///Starts with no field dirty, as when the receiver already has `value`
+    pub fn new(value: T) -> Self {
+        let dirty = vec![false; T::field_chunks().len()];
+        Tracked { value, dirty }
+    }
+
+    ///Writes through `f`, marking field `index` dirty. Panics before writing when `index` is not a field of `T`
+    pub fn modify<R>(&mut self, index: usize, f: impl FnOnce(&mut T) -> R) -> R {
+        self.mark_dirty(index);
+        f(&mut self.value)
+    }
+
+    ///Panics when `index` is not a field of `T`, as slice indexing does, since the change would never be sent
+    pub fn mark_dirty(&mut self, index: usize) {
+        assert!(index < self.dirty.len(), "field {} of a struct with {} fields", index, self.dirty.len());
+        self.dirty[index] = true;
+    }
+
+    pub fn is_dirty(&self) -> bool {
+        self.dirty.contains(&true)
+    }
+
+    ///The field mask and the dirty fields, then clears the mask
+    pub fn compress_dirty(&mut self) -> Result<Compressed, CompressError> {
+        let mut res = self.dirty.iter().map(|&dirty| dirty as u8).collect::<Vec<u8>>();
+        for (index, _) in self.dirty.iter().enumerate().filter(|(_, dirty)| **dirty) {
+            self.value.compress_field(index)?.extend_to_res(&mut res);
+        }
+        self.dirty.iter_mut().for_each(|dirty| *dirty = false);
+        Ok(Compressed::Binaries(res))
+    }
+
+    pub fn into_inner(self) -> T {
+        self.value
+    }
+}
+
+//read only, writes go through `modify` so they are tracked
+impl<T> Deref for Tracked<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
+
+///Applies what `Tracked::compress_dirty` wrote
+pub fn apply_dirty<T: DirtyFields>(value: &mut T, compressed: Compressed) -> Result<(), DecompressError> {
+    let mut binaries = compressed.to_binaries();
+    apply_dirty_from_binaries(value, &mut binaries)
+}
+
+fn apply_dirty_from_binaries<T: DirtyFields>(value: &mut T, compressed: &mut Vec<u8>) -> Result<(), DecompressError> {
+    let chunks = T::field_chunks();
+    if compressed.len() < chunks.len() {
+        return Err(DecompressError::create(DecompressError::WrongBytesLength(format!("Not enough bytes for {} dirty fields", chunks.len()))))
+    }
+    let mask = compressed.drain( .. chunks.len()).collect::<Vec<u8>>();
+    for (index, chunk) in chunks.iter().enumerate() {
+        if mask[index] == 1 {
+            value.decompress_field(index, compressed, chunk)?;
+        }
+    }
+    Ok(())
+}
+
+///The dirty fields of the rows that have any, then clears them
+//dirty row count, the gap before each dirty row, then each row's mask and fields
+pub fn compress_dirty_rows<T: DirtyFields>(rows: &mut [Tracked<T>]) -> Result<Compressed, CompressError> {
+    let dirty = rows.iter().enumerate().filter(|(_, row)| row.is_dirty()).map(|(i, _)| i).collect::<Vec<usize>>();
+
+    let mut res = compress_varint(dirty.len() as u128);
+    let mut next = 0;
+    for &i in dirty.iter() {
+        res.extend(compress_varint((i - next) as u128));
+        next = i + 1;
+    }
+    for i in dirty {
+        rows[i].compress_dirty()?.extend_to_res(&mut res);
+    }
+    Ok(Compressed::Binaries(res))
+}
+
+///Applies what `compress_dirty_rows` wrote to the same rows on the receiving side
+pub fn apply_dirty_rows<T: DirtyFields>(rows: &mut [T], compressed: Compressed) -> Result<(), DecompressError> {
+    let mut binaries = compressed.to_binaries();
+    let count = calc_delimeter_size(&mut binaries, 7)?;
+
+    let mut dirty = vec![];
+    let mut next: u128 = 0;
+    for _ in 0 .. count {
+        let i = next.checked_add(calc_delimeter_size(&mut binaries, 7)?).ok_or(DecompressError::create(DecompressError::WrongBytesLength(String::from("dirty row gap overflows u128"))))?;
+        if i >= rows.len() as u128 {
+            return Err(DecompressError::create(DecompressError::WrongBytesLength(format!("dirty row {} of {}", i, rows.len()))))
+        }
+        dirty.push(i as usize);
+        next = i + 1;
+    }
+
+    for i in dirty {
+        apply_dirty_from_binaries(&mut rows[i], &mut binaries)?;
+    }
+    Ok(())
+}
@@ -42,6 +42,17 @@ pub enum DecompressError {
     FromBytesErr(String),
     FromBinariesErr(String),
     PrimitiveDataErr(String),
+    DepthLimitExceeded(String),
+    SharedRefErr(String),
+    UnsupportedCodec(String),
+    DictionaryMismatch(String),
+    SessionOutOfSync(String),
+    BadMagic(String),
+    UnsupportedVersion(String),
+    ChecksumMismatch(String),
+    SchemaMismatch(String),
+    UnknownVariantErr(String),
+    LengthLimitExceeded(String),
 }
 
 impl DecompressError {
@@ -62,6 +73,39 @@ impl DecompressError {
             Self::PrimitiveDataErr(_) => {
                 DecompressError::PrimitiveDataErr(format!("Decompressing primitive data type directly is not supported currently, Wrap it in a struct, and derive it with COmprezable instead"))
             }
+            Self::DepthLimitExceeded(s) => {
+                DecompressError::DepthLimitExceeded(format!("Decompress Error; Nesting depth limit exceeded; {}", s)) //limit: 128
+            },
+            Self::SharedRefErr(s) => {
+                DecompressError::SharedRefErr(format!("Decompress Error; Invalid shared reference; {}", s)) //index: 3, known: 2
+            },
+            Self::UnsupportedCodec(s) => {
+                DecompressError::UnsupportedCodec(format!("Decompress Error; Codec not supported by this build; {}", s)) //deflate, enable the `deflate` feature
+            },
+            Self::DictionaryMismatch(s) => {
+                DecompressError::DictionaryMismatch(format!("Decompress Error; Trained dictionary mismatch; {}", s)) //written with 1a2b3c4d, decoding with 5e6f7a8b
+            },
+            Self::SessionOutOfSync(s) => {
+                DecompressError::SessionOutOfSync(format!("Decompress Error; Session out of sync, a keyframe is needed; {}", s)) //message 12, expected 11
+            },
+            Self::BadMagic(s) => {
+                DecompressError::BadMagic(format!("Decompress Error; Not a comprez message, bad magic; {}", s)) //found: 7b22
+            },
+            Self::UnsupportedVersion(s) => {
+                DecompressError::UnsupportedVersion(format!("Decompress Error; Format version not supported; {}", s)) //version: 3, supported: 1
+            },
+            Self::ChecksumMismatch(s) => {
+                DecompressError::ChecksumMismatch(format!("Decompress Error; Checksum mismatch, the message is corrupted; {}", s)) //Crc32c [1f, 00, 9a, 3c], computed [5e, 02, 9a, 3c]
+            },
+            Self::SchemaMismatch(s) => {
+                DecompressError::SchemaMismatch(format!("Decompress Error; Message was written by another schema; {}", s)) //expected: 9f3c61a0d2e84b17, found: 5e0b7c19a4f2d368
+            },
+            Self::UnknownVariantErr(s) => {
+                DecompressError::UnknownVariantErr(format!("Decompress Error; Enum variant is not known and there is no fallback; {}", s)) //variant: 5, payload: 12 bits
+            },
+            Self::LengthLimitExceeded(s) => {
+                DecompressError::LengthLimitExceeded(format!("Decompress Error; Decoded length limit exceeded; {}", s)) //length: 4294967296, limit: 16777216
+            },
             Self::Unknown(s) => {
                 DecompressError::Unknown(s)
             }
@@ -0,0 +1,206 @@
+//! PNG-style predictor filters in front of the LZ4 `Vec<u8>` path.
+//!
+//! Image and audio buffers rarely repeat byte for byte, but neighbouring samples are close.
+//! [`Filtered`] stores every byte as its difference from a prediction made out of its
+//! neighbours, which LZ4 then finds far more matches in. The filter is either picked by the
+//! caller or, with [`Filter::Auto`], chosen per buffer by trying each one and keeping the smallest.
+//! An `Auto` buffer decodes with its filter still set to `Auto`.
+//!
+//! Neighbours are `stride` bytes back on the same row (one pixel or sample) and `row_len`
+//! bytes back on the row above. A `row_len` of 0 treats the buffer as a single row.
+//!
+//! ## Example
+//! ```rust,ignore
+//! use comprez::filter::{Filter, Filtered};
+//!
+//! #[derive(Comprezable, Debug)]
+//! struct Frame {
+//!     //RGB, 640 pixels per row
+//!     pixels: Filtered,
+//!     //16-bit samples
+//!     audio: Filtered,
+//! }
+//!
+//! let frame = Frame {
+//!     pixels: Filtered { stride: 3, row_len: 640 * 3, ..Filtered::new(pixels) },
+//!     audio: Filtered { filter: Filter::Sub, stride: 2, ..Filtered::new(samples) },
+//! };
+//! ```
+
+use crate::{comprezable::{calc_delimeter_size, compress_varint, push_bits, take_bits, Comprezable}, error::{CompressError, DecompressError}, schema::{record_field, SchemaHasher}, BinaryChunk, Compressed};
+
+const FILTER_BITS: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Filter {
+    ///Bytes as they are
+    None,
+    ///Difference from the previous sample on the row, a plain byte-delta when stride is 1
+    Sub,
+    ///Difference from the row above
+    Up,
+    ///Difference from the mean of the previous sample and the row above
+    Average,
+    ///Difference from whichever of left, above and above-left is nearest to left + above - above-left
+    Paeth,
+    ///Tries each filter and keeps the one that compresses smallest
+    #[default]
+    Auto,
+}
+
+impl Filter {
+    const CANDIDATES: [Filter; 5] = [Filter::None, Filter::Sub, Filter::Up, Filter::Average, Filter::Paeth];
+
+    //Auto is written as the filter it picked, it has no id of its own
+    fn id(self) -> Result<u128, CompressError> {
+        match self {
+            Filter::None => Ok(0),
+            Filter::Sub => Ok(1),
+            Filter::Up => Ok(2),
+            Filter::Average => Ok(3),
+            Filter::Paeth => Ok(4),
+            Filter::Auto => Err(CompressError::create(CompressError::EncodeErr(String::from("Compress Error; Filter::Auto has no id")))),
+        }
+    }
+
+    fn from_id(id: u128) -> Result<Self, DecompressError> {
+        Filter::CANDIDATES.get(id as usize).copied().ok_or(DecompressError::create(DecompressError::BinariesToIntErr(format!("filter id: {}", id))))
+    }
+
+    fn predict(self, left: u8, above: u8, above_left: u8) -> u8 {
+        match self {
+            Filter::None | Filter::Auto => 0,
+            Filter::Sub => left,
+            Filter::Up => above,
+            Filter::Average => ((left as u16 + above as u16) / 2) as u8,
+            Filter::Paeth => {
+                let estimate = left as i16 + above as i16 - above_left as i16;
+                let (to_left, to_above, to_above_left) = ((estimate - left as i16).abs(), (estimate - above as i16).abs(), (estimate - above_left as i16).abs());
+                if to_left <= to_above && to_left <= to_above_left {
+                    left
+                } else if to_above <= to_above_left {
+                    above
+                } else {
+                    above_left
+                }
+            },
+        }
+    }
+}
+
+///Byte buffer run through a predictor filter before LZ4
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Filtered {
+    pub data: Vec<u8>,
+    pub filter: Filter,
+    ///Bytes per pixel or sample
+    pub stride: usize,
+    ///Bytes per row, 0 for a single row
+    pub row_len: usize,
+}
+
+impl Filtered {
+    ///Auto filter over single bytes in a single row
+    pub fn new(data: Vec<u8>) -> Self {
+        Filtered { data, filter: Filter::Auto, stride: 1, row_len: 0 }
+    }
+}
+
+impl Default for Filtered {
+    fn default() -> Self {
+        Filtered::new(vec![])
+    }
+}
+
+impl Comprezable for Filtered {
+    fn compress(self) -> Result<Compressed, CompressError> {
+        Err(CompressError::create(CompressError::DataNoSupported(String::new())))
+    }
+
+    //filter id, auto flag, stride, row length, then the filtered bytes through Vec<u8>
+    fn compress_to_binaries(self, _max_num: Option<u128>) -> Result<Compressed, CompressError> {
+        if self.stride == 0 {
+            return Err(CompressError::create(CompressError::EncodeErr(String::from("Compress Error; Filtered stride must be at least 1"))))
+        }
+
+        let candidates = match self.filter {
+            Filter::Auto => Filter::CANDIDATES.to_vec(),
+            filter => vec![filter],
+        };
+        let mut best: Option<(Filter, Vec<u8>)> = None;
+        for filter in candidates {
+            let bytes = apply_filter(&self.data, filter, self.stride, self.row_len).compress_to_binaries(None)?.to_binaries();
+            match &best {
+                Some((_, best)) if best.len() <= bytes.len() => {},
+                _ => best = Some((filter, bytes)),
+            }
+        }
+        let (filter, bytes) = best.unwrap();
+
+        let mut res = vec![];
+        push_bits(&mut res, filter.id()?, FILTER_BITS);
+        //an Auto buffer decodes as Auto again, not as the filter it happened to pick
+        res.push((self.filter == Filter::Auto) as u8);
+        res.extend(compress_varint(self.stride as u128));
+        res.extend(compress_varint(self.row_len as u128));
+        res.extend(bytes);
+        Ok(Compressed::Binaries(res))
+    }
+
+    fn max_binaries(max_num: Option<u128>) -> BinaryChunk {
+        let _field = record_field::<Self>(max_num);
+        BinaryChunk::Delimeter
+    }
+
+    fn schema_hash(_max_num: Option<u128>) -> u64 {
+        SchemaHasher::new("Filtered").finish()
+    }
+
+    fn decompress(_compressed: Compressed) -> Result<Self, DecompressError> where Self: Sized {
+        Err(DecompressError::create(DecompressError::PrimitiveDataErr(String::new())))
+    }
+
+    fn decompress_from_binaries(compressed: &mut Vec<u8>, _bit_size: Option<usize>) -> Result<Self, DecompressError> where Self:Sized {
+        let filter = Filter::from_id(take_bits(compressed, FILTER_BITS)?)?;
+        let auto = take_bits(compressed, 1)? == 1;
+        let stride = calc_delimeter_size(compressed, 7)? as usize;
+        let row_len = calc_delimeter_size(compressed, 7)? as usize;
+        if stride == 0 {
+            return Err(DecompressError::create(DecompressError::BinariesToIntErr(format!("filter stride: {}", stride))))
+        }
+
+        let filtered = Vec::<u8>::decompress_from_binaries(compressed, None)?;
+        let data = reverse_filter(&filtered, filter, stride, row_len);
+        let filter = if auto { Filter::Auto } else { filter };
+        Ok(Filtered { data, filter, stride, row_len })
+    }
+}
+
+//left, above and above-left of byte i, 0 past the edges
+fn neighbours(data: &[u8], i: usize, stride: usize, row_len: usize) -> (u8, u8, u8) {
+    let column = if row_len == 0 { i } else { i % row_len };
+    let has_left = column >= stride;
+    let has_above = row_len > 0 && i >= row_len;
+
+    let left = if has_left { data[i - stride] } else { 0 };
+    let above = if has_above { data[i - row_len] } else { 0 };
+    let above_left = if has_left && has_above { data[i - row_len - stride] } else { 0 };
+    (left, above, above_left)
+}
+
+fn apply_filter(data: &[u8], filter: Filter, stride: usize, row_len: usize) -> Vec<u8> {
+    (0 .. data.len()).map(|i| {
+        let (left, above, above_left) = neighbours(data, i, stride, row_len);
+        data[i].wrapping_sub(filter.predict(left, above, above_left))
+    }).collect()
+}
+
+//predicts from the bytes already restored, so they must be restored in order
+fn reverse_filter(filtered: &[u8], filter: Filter, stride: usize, row_len: usize) -> Vec<u8> {
+    let mut data = Vec::with_capacity(filtered.len());
+    for (i, byte) in filtered.iter().enumerate() {
+        let (left, above, above_left) = neighbours(&data, i, stride, row_len);
+        data.push(byte.wrapping_add(filter.predict(left, above, above_left)));
+    }
+    data
+}
@@ -0,0 +1,94 @@
+//! Forward-compatible enums, for decoders older than the variants they receive.
+//!
+//! `comprez_enum_val` writes a variant index and then the payload, so a decoder that does not know
+//! the index cannot tell where the payload ends. A framed enum leads each payload with its bit
+//! length instead. An old decoder then keeps any variant it does not know as an [`UnknownVariant`]
+//! in its `#[comprez(other)]` fallback variant, index and payload bits intact, and writes it back
+//! unchanged with [`UnknownVariant::to_framed`]. Enums with no fallback fail with `UnknownVariantErr`.
+//!
+//! Framing costs one varint per variant, usually 8 bits.
+//!
+//! ## Example
+//! ```rust,ignore
+//! use comprez::framed::{comprez_framed_enum_val, decompress_framed_payload, read_framed_variant, UnknownVariant};
+//!
+//! #[derive(Debug, Clone)]
+//! enum Event {
+//!     Spawn(u16), //#[maxNum=1000]
+//!     Chat(String),
+//!     Other(UnknownVariant), //#[comprez(other)]
+//! }
+//!
+//! //in `impl Comprezable for Event`
+//! fn compress_to_binaries(self, _max_num: Option<u128>) -> Result<Compressed, CompressError> {
+//!     match self {
+//!         Event::Spawn(id) => comprez_framed_enum_val(Some(id), 0, Some(1000)),
+//!         Event::Chat(text) => comprez_framed_enum_val(Some(text), 1, None),
+//!         Event::Other(unknown) => Ok(unknown.to_framed()),
+//!     }
+//! }
+//!
+//! fn decompress_from_binaries(compressed: &mut Vec<u8>, _bit_size: Option<usize>) -> Result<Self, DecompressError> {
+//!     match read_framed_variant(compressed)? {
+//!         UnknownVariant { index: 0, payload } => Ok(Event::Spawn(decompress_framed_payload(payload, Some(1000))?)),
+//!         UnknownVariant { index: 1, payload } => Ok(Event::Chat(decompress_framed_payload(payload, None)?)),
+//!         unknown => Ok(Event::Other(unknown)),
+//!     }
+//! }
+//! ```
+
+use std::fmt::Debug;
+
+use crate::{comprezable::{calc_delimeter_size, compress_enum_index, compress_varint, Comprezable}, error::{CompressError, DecompressError}, Compressed};
+
+///A variant this decoder does not know, kept as it was read
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct UnknownVariant {
+    pub index: usize,
+    ///Payload binaries, empty for a unit variant
+    pub payload: Vec<u8>,
+}
+
+impl UnknownVariant {
+    ///The variant as it was read, for sending it on
+    pub fn to_framed(&self) -> Compressed {
+        frame(self.index, self.payload.clone())
+    }
+
+    ///`UnknownVariantErr` for enums with no fallback variant
+    pub fn into_err(self) -> DecompressError {
+        DecompressError::create(DecompressError::UnknownVariantErr(format!("variant: {}, payload: {} bits", self.index, self.payload.len())))
+    }
+}
+
+//variant index, payload bit length, then the payload
+fn frame(index: usize, payload: Vec<u8>) -> Compressed {
+    let mut res = compress_enum_index(index).to_binaries();
+    res.extend(compress_varint(payload.len() as u128));
+    res.extend(payload);
+    Compressed::Binaries(res)
+}
+
+///`comprez_enum_val` with the payload led by its bit length, `val` is None for a unit variant
+pub fn comprez_framed_enum_val<T: Comprezable + Clone + Debug>(val: Option<T>, n: usize, max_num: Option<u128>) -> Result<Compressed, CompressError> {
+    let payload = match val {
+        Some(t) => t.compress_to_binaries(max_num)?.to_binaries(),
+        None => vec![],
+    };
+    Ok(frame(n, payload))
+}
+
+///Index and payload of the next framed variant, known or not
+pub fn read_framed_variant(compressed: &mut Vec<u8>) -> Result<UnknownVariant, DecompressError> {
+    let index = calc_delimeter_size(compressed, 4)? as usize;
+    let len = calc_delimeter_size(compressed, 7)? as usize;
+    if compressed.len() < len {
+        return Err(DecompressError::create(DecompressError::WrongBytesLength(format!("variant payload of {} bits is out of bound", len))))
+    }
+    Ok(UnknownVariant { index, payload: compressed.drain( .. len).collect() })
+}
+
+///Payload of a known variant, `max_num` as its attribute gives it
+pub fn decompress_framed_payload<T: Comprezable>(mut payload: Vec<u8>, max_num: Option<u128>) -> Result<T, DecompressError> {
+    T::max_binaries(max_num).decompress(&mut payload)
+}
@@ -0,0 +1,239 @@
+//! 2D grids such as tilemaps and occupancy maps.
+//!
+//! A `Vec<Vec<T>>` pays a length for every row and never notices that a row repeats the one above.
+//! [`Grid`] stores the width and height once and picks the smallest of:
+//! - raw cells, row-major
+//! - 2D run-length: runs of one value along the row, and runs copied from the row above
+//! - quadtree: uniform squares written once, the rest split in four
+//!
+//! ## Example
+//! ```rust,ignore
+//! use comprez::grid::Grid;
+//!
+//! #[derive(Comprezable, Debug)]
+//! struct Level {
+//!     #[maxNum=500]
+//!     tiles: Grid<u16>,
+//! }
+//!
+//! let level = Level { tiles: Grid::from_rows(rows).unwrap() };
+//! let rows: Vec<Vec<u16>> = decompressed.tiles.into_rows();
+//! ```
+
+use std::fmt::Debug;
+
+use crate::{comprezable::{calc_delimeter_size, check_decode_len, compress_elements, compress_varint, decompress_elements, encode_smallest, nested_decode, nested_max_binaries, push_bits, take_bits, Comprezable}, error::{CompressError, DecompressError}, schema::{record_field, SchemaHasher}, BinaryChunk, Compressed};
+
+const RAW: u128 = 0;
+const RUNS: u128 = 1;
+const QUADTREE: u128 = 2;
+const MODE_BITS: usize = 2;
+
+//x, y, width, height
+type Region = (usize, usize, usize, usize);
+
+///Rectangular grid of cells in row-major order
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub struct Grid<T> {
+    pub width: usize,
+    pub height: usize,
+    pub cells: Vec<T>,
+}
+
+impl<T> Grid<T> {
+    ///Errors when the rows are not all the same length
+    pub fn from_rows(rows: Vec<Vec<T>>) -> Result<Self, CompressError> {
+        let height = rows.len();
+        let width = rows.first().map(|row| row.len()).unwrap_or(0);
+        if rows.iter().any(|row| row.len() != width) {
+            return Err(CompressError::create(CompressError::EncodeErr(String::from("Compress Error; Grid rows are not the same length"))))
+        }
+        Ok(Grid { width, height, cells: rows.into_iter().flatten().collect() })
+    }
+
+    pub fn into_rows(self) -> Vec<Vec<T>> {
+        let mut cells = self.cells.into_iter();
+        (0 .. self.height).map(|_| cells.by_ref().take(self.width).collect()).collect()
+    }
+}
+
+impl<T, const W: usize, const H: usize> From<[[T; W]; H]> for Grid<T> {
+    fn from(matrix: [[T; W]; H]) -> Self {
+        Grid { width: W, height: H, cells: matrix.into_iter().flatten().collect() }
+    }
+}
+
+impl<T: Comprezable + PartialEq + Clone + Debug> Comprezable for Grid<T> {
+    fn compress(self) -> Result<Compressed, CompressError> {
+        Err(CompressError::create(CompressError::DataNoSupported(String::new())))
+    }
+
+    //width, height, mode, then the cells in that mode
+    fn compress_to_binaries(self, max_num: Option<u128>) -> Result<Compressed, CompressError> {
+        if self.cells.len() != self.width * self.height {
+            return Err(CompressError::create(CompressError::EncodeErr(format!("Compress Error; Grid of {}x{} holds {} cells", self.width, self.height, self.cells.len()))))
+        }
+
+        let (mode, cells) = encode_smallest(&[RAW, RUNS, QUADTREE], |mode| match mode {
+            RUNS => compress_runs(&self, max_num),
+            QUADTREE => {
+                let mut quadtree = vec![];
+                compress_quadtree(&self, (0, 0, self.width, self.height), max_num, &mut quadtree)?;
+                Ok(quadtree)
+            },
+            _ => compress_elements(self.cells.clone(), max_num).map(|raw| raw.to_binaries()),
+        })?;
+
+        let mut res = compress_varint(self.width as u128);
+        res.extend(compress_varint(self.height as u128));
+        push_bits(&mut res, mode, MODE_BITS);
+        res.extend(cells);
+        Ok(Compressed::Binaries(res))
+    }
+
+    fn max_binaries(max_num: Option<u128>) -> BinaryChunk {
+        let _field = record_field::<Self>(max_num);
+        nested_max_binaries::<T>(max_num)
+    }
+
+    fn schema_hash(max_num: Option<u128>) -> u64 {
+        SchemaHasher::new("Grid").field::<T>(max_num).finish()
+    }
+
+    fn decompress(_compressed: Compressed) -> Result<Self, DecompressError> where Self: Sized {
+        Err(DecompressError::create(DecompressError::PrimitiveDataErr(String::new())))
+    }
+
+    fn decompress_from_binaries(compressed: &mut Vec<u8>, bit_size: Option<usize>) -> Result<Self, DecompressError> where Self:Sized {
+        let width = calc_delimeter_size(compressed, 7)? as usize;
+        let height = calc_delimeter_size(compressed, 7)? as usize;
+        let len = width.checked_mul(height).ok_or(DecompressError::create(DecompressError::WrongBytesLength(format!("grid of {}x{}", width, height))))?;
+        //runs and uniform quadtree squares cover any number of cells in a few bits
+        let len = check_decode_len(len as u128)?;
+
+        let cells = nested_decode(|| match take_bits(compressed, MODE_BITS)? {
+            RAW => decompress_elements(compressed, len as u128, bit_size),
+            RUNS => decompress_runs(compressed, width, len, bit_size),
+            QUADTREE => {
+                let mut cells = vec![None; len];
+                decompress_quadtree(compressed, (0, 0, width, height), width, bit_size, &mut cells)?;
+                Ok(cells.into_iter().map(|cell| cell.unwrap()).collect())
+            },
+            mode => Err(DecompressError::create(DecompressError::BinariesToIntErr(format!("grid mode: {}", mode)))),
+        })?;
+
+        Ok(Grid { width, height, cells })
+    }
+}
+
+//flag 0: (length - 1, value) run along the row, flag 1: (length - 1) cells copied from the row above
+fn compress_runs<T: Comprezable + PartialEq + Clone>(grid: &Grid<T>, max_num: Option<u128>) -> Result<Vec<u8>, CompressError> {
+    let cells = &grid.cells;
+    let mut res = vec![];
+    let mut i = 0;
+    while i < cells.len() {
+        let same = cells[i ..].iter().take_while(|cell| **cell == cells[i]).count();
+        let up = if i >= grid.width {
+            (i .. cells.len()).take_while(|&j| cells[j] == cells[j - grid.width]).count()
+        } else {
+            0
+        };
+
+        if up >= same {
+            res.push(1);
+            res.extend(compress_varint(up as u128 - 1));
+            i += up;
+        } else {
+            res.push(0);
+            res.extend(compress_varint(same as u128 - 1));
+            cells[i].clone().compress_to_binaries(max_num)?.extend_to_res(&mut res);
+            i += same;
+        }
+    }
+    Ok(res)
+}
+
+fn decompress_runs<T: Comprezable + Clone>(compressed: &mut Vec<u8>, width: usize, len: usize, bit_size: Option<usize>) -> Result<Vec<T>, DecompressError> {
+    let mut cells: Vec<T> = vec![];
+    while cells.len() < len {
+        let copy_up = take_bits(compressed, 1)? == 1;
+        let run = calc_delimeter_size(compressed, 7)?.checked_add(1).ok_or(DecompressError::create(DecompressError::WrongBytesLength(String::from("grid run length overflows u128"))))?;
+        if run > (len - cells.len()) as u128 || (copy_up && cells.len() < width) {
+            return Err(DecompressError::create(DecompressError::WrongBytesLength(format!("grid run of {} at cell {}", run, cells.len()))))
+        }
+
+        if copy_up {
+            for _ in 0 .. run {
+                cells.push(cells[cells.len() - width].clone());
+            }
+        } else {
+            let cell = T::decompress_from_binaries(compressed, bit_size)?;
+            for _ in 0 .. run {
+                cells.push(cell.clone());
+            }
+        }
+    }
+    Ok(cells)
+}
+
+//flag 1 + value for a uniform region, flag 0 and its four quarters otherwise, single cells need no flag
+fn compress_quadtree<T: Comprezable + PartialEq + Clone>(grid: &Grid<T>, region: Region, max_num: Option<u128>, res: &mut Vec<u8>) -> Result<(), CompressError> {
+    let (x, y, width, height) = region;
+    if width == 0 || height == 0 {
+        return Ok(())
+    }
+
+    let first = &grid.cells[y * grid.width + x];
+    if width == 1 && height == 1 {
+        return first.clone().compress_to_binaries(max_num).map(|cell| cell.extend_to_res(res))
+    }
+
+    let uniform = (y .. y + height).all(|row| {
+        grid.cells[row * grid.width + x .. row * grid.width + x + width].iter().all(|cell| cell == first)
+    });
+    if uniform {
+        res.push(1);
+        return first.clone().compress_to_binaries(max_num).map(|cell| cell.extend_to_res(res))
+    }
+
+    res.push(0);
+    for quarter in quarters(region) {
+        compress_quadtree(grid, quarter, max_num, res)?;
+    }
+    Ok(())
+}
+
+fn decompress_quadtree<T: Comprezable + Clone>(compressed: &mut Vec<u8>, region: Region, grid_width: usize, bit_size: Option<usize>, cells: &mut [Option<T>]) -> Result<(), DecompressError> {
+    let (x, y, width, height) = region;
+    if width == 0 || height == 0 {
+        return Ok(())
+    }
+
+    let uniform = width == 1 && height == 1 || take_bits(compressed, 1)? == 1;
+    if uniform {
+        let cell = T::decompress_from_binaries(compressed, bit_size)?;
+        for row in y .. y + height {
+            for col in x .. x + width {
+                cells[row * grid_width + col] = Some(cell.clone());
+            }
+        }
+        return Ok(())
+    }
+
+    for quarter in quarters(region) {
+        decompress_quadtree(compressed, quarter, grid_width, bit_size, cells)?;
+    }
+    Ok(())
+}
+
+fn quarters(region: Region) -> [Region; 4] {
+    let (x, y, width, height) = region;
+    let left = width.div_ceil(2);
+    let top = height.div_ceil(2);
+    [
+        (x, y, left, top),
+        (x + left, y, width - left, top),
+        (x, y + top, left, height - top),
+        (x + left, y + top, width - left, height - top),
+    ]
+}
@@ -61,8 +61,9 @@
 //! ```
 //! 
 //! ## Note
-//! Since the compression of Vec<u8> uses LZ4 flex crate, compressing small vectors might increase the space instead. 
-//! However in the future, self implementation will be created.
+//! Vec<u8> is compressed with the LZ4 flex crate by default (Deflate and Zstd behind the `deflate` and `zstd`
+//! features, see `comprez::codec`), or stored as is when that would make it larger
+//! (small vectors, or data that is compressed already such as JPEG, PNG, gzip and zstd).
 
 
 use comprezable::Comprezable;
@@ -73,6 +74,64 @@ pub mod error;
 /// Trait for compressing structs
 pub mod comprezable;
 
+///Deduplication of shared Rc/Arc
+pub mod shared;
+
+///Delta encoding for Vec of integers
+pub mod delta;
+
+///Run-length encoding for Vec
+pub mod rle;
+
+///Patched bitpacking for Vec of bounded integers
+pub mod bitpack;
+
+///Sparse encoding for mostly-default Vec
+pub mod sparse;
+
+///Column by column compression for Vec of structs
+pub mod columnar;
+
+///Dictionary encoding for low-cardinality Vec
+pub mod dictionary;
+
+///2D grids and tilemaps
+pub mod grid;
+
+///Predictor filters for byte buffers
+pub mod filter;
+
+///Pluggable byte codecs for Vec<u8>
+pub mod codec;
+
+///Trained dictionaries for small similar byte payloads
+#[cfg(feature = "lz4")]
+pub mod trained;
+
+///Whole-message options for compress_with and decompress_with
+pub mod options;
+
+///CRC32C and xxHash64 checksums for whole messages
+pub mod checksum;
+
+///Schema fingerprints of Comprezable types
+pub mod schema;
+
+///Stateful encoder and decoder for a stream of messages
+pub mod session;
+
+///Diff and patch between two values of the same type
+pub mod diff;
+
+///Dirty-field tracking for incremental updates
+pub mod dirty;
+
+///Versioned structs that decode payloads of older and newer versions
+pub mod versioned;
+
+///Forward-compatible enums with a fallback for unknown variants
+pub mod framed;
+
 
 
 
@@ -0,0 +1,175 @@
+//! Whole-message options for `compress_with` and `decompress_with`.
+//!
+//! Bounded-integer packing removes redundancy inside a value, not across a large message.
+//! With [`Options::post_codec`] set, the packed message is run through a byte codec as a final
+//! pass, which picks up the repetition between the 50k similar structs of a `Vec`.
+//! One flag bit records whether the pass paid off; when it would not shrink the message it is skipped.
+//!
+//! With [`Options::header`] set, the message starts with a 4 byte header: the magic `CZ`, the
+//! format version and a flags byte naming the features used. Decoding checks the header and
+//! follows its flags, errors are `BadMagic` for bytes that are not a comprez message and
+//! `UnsupportedVersion` for a newer format. Without a header, a message must be decompressed
+//! with the same options it was compressed with.
+//!
+//! With [`Options::checksum`] set, a checksum of the whole message, header included, is appended
+//! and checked before anything is decoded (see `comprez::checksum`). With [`Options::schema`] set,
+//! the type's `schema_hash` goes in front of the message and decoding as another type or layout
+//! fails with `SchemaMismatch` (see `comprez::schema`).
+//!
+//! ## Example
+//! ```rust,ignore
+//! use comprez::{checksum::Checksum, codec::Codec, options::Options};
+//!
+//! let options = Options { post_codec: Some(Codec::Lz4), header: true, checksum: Some(Checksum::Crc32c), schema: true };
+//! let compressed = readings.compress_with(&options)?;
+//! let decompressed = Readings::decompress_with(compressed, &options)?;
+//! ```
+
+use crate::{checksum::{append_checksum, verify_checksum, Checksum}, codec::{decode_bytes, encode_bytes, push_codec_id, take_codec_id, Codec}, comprezable::{push_bits, take_bits, Comprezable}, error::{CompressError, DecompressError}, Compressed};
+
+///First two bytes of a message with a header
+pub const MAGIC: [u8; 2] = *b"CZ";
+///Format version written in the header, raised whenever a layout changes
+pub const FORMAT_VERSION: u8 = 1;
+
+const HEADER_BITS: usize = 32;
+//flags byte of the header
+const FLAG_POST_CODEC: u8 = 1;
+const FLAG_CRC32C: u8 = 1 << 1;
+const FLAG_XXHASH64: u8 = 1 << 2;
+const FLAG_SCHEMA: u8 = 1 << 3;
+const KNOWN_FLAGS: u8 = FLAG_POST_CODEC | FLAG_CRC32C | FLAG_XXHASH64 | FLAG_SCHEMA;
+const SCHEMA_BITS: usize = 64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Options {
+    ///Codec for a final pass over the whole packed message, None for no pass
+    pub post_codec: Option<Codec>,
+    ///Magic, format version and flags in front of the message
+    pub header: bool,
+    ///Checksum trailer over the whole message
+    pub checksum: Option<Checksum>,
+    ///Schema hash of the type in front of the message
+    pub schema: bool,
+}
+
+impl Options {
+    fn flags(&self) -> u8 {
+        let mut flags = 0;
+        if self.post_codec.is_some() {
+            flags |= FLAG_POST_CODEC;
+        }
+        match self.checksum {
+            Some(Checksum::Crc32c) => flags |= FLAG_CRC32C,
+            Some(Checksum::XxHash64) => flags |= FLAG_XXHASH64,
+            None => {},
+        }
+        if self.schema {
+            flags |= FLAG_SCHEMA;
+        }
+        flags
+    }
+}
+
+fn checksum_of(flags: u8) -> Result<Option<Checksum>, DecompressError> {
+    match (flags & FLAG_CRC32C != 0, flags & FLAG_XXHASH64 != 0) {
+        (true, true) => Err(DecompressError::create(DecompressError::UnsupportedVersion(format!("two checksums in flags: {:08b}", flags)))),
+        (true, false) => Ok(Some(Checksum::Crc32c)),
+        (false, true) => Ok(Some(Checksum::XxHash64)),
+        (false, false) => Ok(None),
+    }
+}
+
+pub(crate) fn compress_message<T: Comprezable>(value: T, options: &Options) -> Result<Compressed, CompressError> {
+    let packed = value.compress()?;
+    let mut body = match options.post_codec {
+        Some(codec) => post_compress(packed, codec)?,
+        None => packed,
+    };
+    if options.schema {
+        let mut hash = vec![];
+        push_bits(&mut hash, T::schema_hash(None) as u128, SCHEMA_BITS);
+        body = Compressed::Binaries(hash).combine(body);
+    }
+
+    let message = match options.header {
+        true => Compressed::Bytes(vec![MAGIC[0], MAGIC[1], FORMAT_VERSION, options.flags()]).combine(body),
+        false => body,
+    };
+    match options.checksum {
+        Some(checksum) => Ok(append_checksum(message, checksum)),
+        None => Ok(message),
+    }
+}
+
+//flag 1 with the codec id and its output to the end, or flag 0 with the packed message as it is
+fn post_compress(packed: Compressed, codec: Codec) -> Result<Compressed, CompressError> {
+    let packed = packed.to_binaries();
+    if let Some((id, encoded)) = encode_bytes(&Compressed::Binaries(packed.clone()).to_bytes(), codec)? {
+        let mut res = vec![1];
+        push_codec_id(&mut res, id);
+        let post = Compressed::Binaries(res).combine(Compressed::Bytes(encoded));
+        if post.to_binaries().len() < packed.len() + 1 {
+            return Ok(post)
+        }
+    }
+    Ok(Compressed::Binaries(vec![0]).combine(Compressed::Binaries(packed)))
+}
+
+pub(crate) fn decompress_message<T: Comprezable>(compressed: Compressed, options: &Options) -> Result<T, DecompressError> {
+    let flags = match options.header {
+        true => read_header(&compressed.to_binaries())?,
+        false => options.flags(),
+    };
+    //the whole message is checked before any of it is decoded
+    let compressed = match checksum_of(flags)? {
+        Some(checksum) => verify_checksum(compressed, checksum)?,
+        None => compressed,
+    };
+
+    let mut binaries = compressed.to_binaries();
+    if options.header {
+        binaries.drain( .. HEADER_BITS);
+    }
+    if flags & FLAG_SCHEMA != 0 {
+        check_schema::<T>(&mut binaries)?;
+    }
+
+    if flags & FLAG_POST_CODEC == 0 || take_bits(&mut binaries, 1)? == 0 {
+        return T::decompress(Compressed::Binaries(binaries))
+    }
+
+    let id = take_codec_id(&mut binaries)?;
+    //whole bytes only, the rest is padding
+    binaries.truncate(binaries.len() / 8 * 8);
+    let (packed, _) = decode_bytes(id, &Compressed::Binaries(binaries).to_bytes())?;
+    T::decompress(Compressed::Bytes(packed))
+}
+
+fn check_schema<T: Comprezable>(binaries: &mut Vec<u8>) -> Result<(), DecompressError> {
+    let expected = T::schema_hash(None);
+    let found = take_bits(binaries, SCHEMA_BITS)? as u64;
+    if found != expected {
+        return Err(DecompressError::create(DecompressError::SchemaMismatch(format!("expected: {:016x}, found: {:016x}", expected, found))))
+    }
+    Ok(())
+}
+
+//the flags, once magic and version check out
+fn read_header(binaries: &[u8]) -> Result<u8, DecompressError> {
+    if binaries.len() < HEADER_BITS {
+        return Err(DecompressError::create(DecompressError::BadMagic(format!("{} bits is shorter than a header", binaries.len()))))
+    }
+    let header = Compressed::Binaries(binaries[.. HEADER_BITS].to_vec()).to_bytes();
+
+    if header[.. 2] != MAGIC {
+        return Err(DecompressError::create(DecompressError::BadMagic(format!("found: {:02x}{:02x}", header[0], header[1]))))
+    }
+    if header[2] != FORMAT_VERSION {
+        return Err(DecompressError::create(DecompressError::UnsupportedVersion(format!("version: {}, supported: {}", header[2], FORMAT_VERSION))))
+    }
+    if header[3] & !KNOWN_FLAGS != 0 {
+        return Err(DecompressError::create(DecompressError::UnsupportedVersion(format!("unknown flags: {:08b}", header[3] & !KNOWN_FLAGS))))
+    }
+    Ok(header[3])
+}
@@ -0,0 +1,135 @@
+//! Run-length encoding for Vec of any Comprezable.
+//!
+//! Each run of equal elements is written once as its length followed by the value,
+//! which pays off for sensor readings, tiles and other data with long runs.
+//!
+//! ## Example
+//! ```rust,ignore
+//! use comprez::rle::{AutoRle, Rle};
+//!
+//! #[derive(Comprezable, Debug)]
+//! struct Tiles {
+//!     #[maxNum=300]
+//!     layer: Rle<u16>, //always run-length encoded
+//!     #[maxNum=300]
+//!     overlay: AutoRle<u16>, //run-length encoded only when runs make up most of it
+//! }
+//! ```
+
+use std::fmt::Debug;
+
+use crate::{comprezable::{calc_delimeter_size, check_decode_len, compress_varint, nested_decode, nested_max_binaries, take_bits, Comprezable}, error::{CompressError, DecompressError}, schema::{record_field, SchemaHasher}, BinaryChunk, Compressed};
+
+///Vec that is always stored as (run length, value) pairs
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub struct Rle<T>(pub Vec<T>);
+
+///Vec stored as (run length, value) pairs when runs make up most of it, as a plain Vec otherwise.
+///One flag bit records the choice.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub struct AutoRle<T>(pub Vec<T>);
+
+impl<T: Comprezable + PartialEq + Clone> Comprezable for Rle<T> {
+    fn compress(self) -> Result<Compressed, CompressError> {
+        Err(CompressError::create(CompressError::DataNoSupported(String::new())))
+    }
+
+    fn compress_to_binaries(self, max_num: Option<u128>) -> Result<Compressed, CompressError> {
+        compress_runs(self.0, max_num)
+    }
+
+    fn max_binaries(max_num: Option<u128>) -> BinaryChunk {
+        let _field = record_field::<Self>(max_num);
+        nested_max_binaries::<T>(max_num)
+    }
+
+    fn schema_hash(max_num: Option<u128>) -> u64 {
+        SchemaHasher::new("Rle").field::<T>(max_num).finish()
+    }
+
+    fn decompress(_compressed: Compressed) -> Result<Self, DecompressError> where Self: Sized {
+        Err(DecompressError::create(DecompressError::PrimitiveDataErr(String::new())))
+    }
+
+    fn decompress_from_binaries(compressed: &mut Vec<u8>, bit_size: Option<usize>) -> Result<Self, DecompressError> where Self:Sized {
+        nested_decode(|| decompress_runs(compressed, bit_size)).map(Rle)
+    }
+}
+
+impl<T: Comprezable + PartialEq + Clone + Debug> Comprezable for AutoRle<T> {
+    fn compress(self) -> Result<Compressed, CompressError> {
+        Err(CompressError::create(CompressError::DataNoSupported(String::new())))
+    }
+
+    fn compress_to_binaries(self, max_num: Option<u128>) -> Result<Compressed, CompressError> {
+        if mostly_runs(&self.0) {
+            Ok(Compressed::Binaries(vec![1]).combine(compress_runs(self.0, max_num)?))
+        } else {
+            Ok(Compressed::Binaries(vec![0]).combine(self.0.compress_to_binaries(max_num)?))
+        }
+    }
+
+    fn max_binaries(max_num: Option<u128>) -> BinaryChunk {
+        let _field = record_field::<Self>(max_num);
+        nested_max_binaries::<T>(max_num)
+    }
+
+    fn schema_hash(max_num: Option<u128>) -> u64 {
+        SchemaHasher::new("AutoRle").field::<T>(max_num).finish()
+    }
+
+    fn decompress(_compressed: Compressed) -> Result<Self, DecompressError> where Self: Sized {
+        Err(DecompressError::create(DecompressError::PrimitiveDataErr(String::new())))
+    }
+
+    fn decompress_from_binaries(compressed: &mut Vec<u8>, bit_size: Option<usize>) -> Result<Self, DecompressError> where Self:Sized {
+        match take_bits(compressed, 1)? {
+            1 => nested_decode(|| decompress_runs(compressed, bit_size)).map(AutoRle),
+            _ => Vec::<T>::decompress_from_binaries(compressed, bit_size).map(AutoRle),
+        }
+    }
+}
+
+//more than half of the elements repeat the one before them
+fn mostly_runs<T: PartialEq>(elements: &[T]) -> bool {
+    let repeated = elements.windows(2).filter(|pair| pair[0] == pair[1]).count();
+    repeated * 2 > elements.len()
+}
+
+//element count, then each run as (length - 1, value)
+fn compress_runs<T: Comprezable + PartialEq>(elements: Vec<T>, max_num: Option<u128>) -> Result<Compressed, CompressError> {
+    let mut res = compress_varint(elements.len() as u128);
+
+    let mut elements = elements.into_iter().peekable();
+    while let Some(element) = elements.next() {
+        let mut run = 1u128;
+        while elements.next_if(|next| *next == element).is_some() {
+            run += 1;
+        }
+        res.extend(compress_varint(run - 1));
+        element.compress_to_binaries(max_num)?.extend_to_res(&mut res);
+    }
+
+    Ok(Compressed::Binaries(res))
+}
+
+fn decompress_runs<T: Comprezable + Clone>(compressed: &mut Vec<u8>, bit_size: Option<usize>) -> Result<Vec<T>, DecompressError> {
+    let count = check_decode_len(calc_delimeter_size(compressed, 7)?)?;
+
+    let mut res: Vec<T> = vec![];
+    let mut remaining = count as u128;
+    while remaining > 0 {
+        let run = calc_delimeter_size(compressed, 7)?.checked_add(1).ok_or(DecompressError::create(DecompressError::WrongBytesLength(String::from("run length overflows u128"))))?;
+        if run > remaining {
+            return Err(DecompressError::create(DecompressError::WrongBytesLength(format!("run of {} is longer than the {} elements left", run, remaining))))
+        }
+        let element = T::decompress_from_binaries(compressed, bit_size)?;
+        for _ in 1 .. run {
+            res.push(element.clone());
+        }
+        res.push(element);
+        remaining -= run;
+    }
+
+    Ok(res)
+}
@@ -0,0 +1,168 @@
+//! Schema fingerprints, for telling a payload of another type or layout apart from garbage.
+//!
+//! `Comprezable::schema_hash(max_num)` is a stable 64 bit FNV-1a hash of a type's encoding: field
+//! order, field types and `maxNum` bounds. Built-in types hash their name and bound.
+//! Any other type, such as a derived struct, hashes the `schema_hash` of each built-in field its
+//! `max_binaries` goes through, in order, and then that layout. Changing a field's type or `maxNum`
+//! changes the hash even when the bit layout stays the same.
+//! A hand-written impl can also list its fields itself with [`SchemaHasher`].
+//!
+//! Enum variant names and their order are not part of a derived hash, so renaming or reordering
+//! variants keeps it. Only a hand-written impl that lists them with [`SchemaHasher::variant`] covers them.
+//!
+//! With [`Options::schema`](crate::options::Options) set, the hash goes in front of the message and
+//! `decompress_with` fails with `SchemaMismatch` when it is not the decoding type's hash.
+//!
+//! ## Example
+//! ```rust,ignore
+//! use comprez::schema::SchemaHasher;
+//!
+//! //in `impl Comprezable for Player`
+//! fn schema_hash(_max_num: Option<u128>) -> u64 {
+//!     SchemaHasher::new("struct")
+//!     .field::<u16>(Some(1000))
+//!     .field::<String>(None)
+//!     .finish()
+//! }
+//! ```
+
+use std::cell::RefCell;
+
+use crate::{comprezable::Comprezable, BinaryChunk};
+
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+///FNV-1a over the parts of a schema, in order
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SchemaHasher(u64);
+
+impl SchemaHasher {
+    ///`tag` names the kind of type, such as "u16", "Vec" or "struct"
+    pub fn new(tag: &str) -> Self {
+        SchemaHasher(FNV_OFFSET).write(tag.as_bytes())
+    }
+
+    pub fn write(self, bytes: &[u8]) -> Self {
+        SchemaHasher(bytes.iter().fold(self.0, |hash, &byte| (hash ^ byte as u64).wrapping_mul(FNV_PRIME)))
+    }
+
+    pub fn write_u64(self, value: u64) -> Self {
+        self.write(&value.to_le_bytes())
+    }
+
+    ///A `maxNum` bound, None hashes apart from every bound
+    pub fn bound(self, max_num: Option<u128>) -> Self {
+        match max_num {
+            Some(max_num) => self.write(&[1]).write(&max_num.to_le_bytes()),
+            None => self.write(&[0]),
+        }
+    }
+
+    ///The next field, `max_num` as its attribute gives it
+    pub fn field<T: Comprezable>(self, max_num: Option<u128>) -> Self {
+        self.write(b"f").write_u64(T::schema_hash(max_num))
+    }
+
+    ///The next enum variant, by name
+    pub fn variant(self, name: &str) -> Self {
+        self.write(b"v").write_u64(name.len() as u64).write(name.as_bytes())
+    }
+
+    pub fn finish(self) -> u64 {
+        self.0
+    }
+}
+
+//a struct being hashed, its fields are written in as their max_binaries run
+struct Recording {
+    name: &'static str,
+    fields: SchemaHasher,
+    //inside a recorded field, whose own hash already covers what it goes through
+    in_field: bool,
+}
+
+thread_local! {
+    static RECORDING: RefCell<Vec<Recording>> = const { RefCell::new(Vec::new()) };
+}
+
+struct RecordingGuard;
+
+impl Drop for RecordingGuard {
+    fn drop(&mut self) {
+        RECORDING.with(|recording| recording.borrow_mut().pop());
+    }
+}
+
+pub(crate) struct FieldGuard(bool);
+
+impl Drop for FieldGuard {
+    fn drop(&mut self) {
+        if self.0 {
+            RECORDING.with(|recording| {
+                if let Some(top) = recording.borrow_mut().last_mut() {
+                    top.in_field = false;
+                }
+            });
+        }
+    }
+}
+
+//called first in the max_binaries of every type with its own schema_hash, writing that hash into the struct being hashed
+pub(crate) fn record_field<T: Comprezable>(max_num: Option<u128>) -> FieldGuard {
+    let recording = RECORDING.with(|recording| {
+        match recording.borrow_mut().last_mut() {
+            Some(top) if !top.in_field => {
+                top.in_field = true;
+                true
+            },
+            _ => false,
+        }
+    });
+    if !recording {
+        return FieldGuard(false)
+    }
+
+    let guard = FieldGuard(true);
+    let hash = T::schema_hash(max_num);
+    RECORDING.with(|recording| {
+        if let Some(top) = recording.borrow_mut().last_mut() {
+            top.fields = top.fields.write(b"f").write_u64(hash);
+        }
+    });
+    guard
+}
+
+//the schema_hash of types that do not give their own: the hash of every built-in field their max_binaries
+//goes through, in order, then the layout. A type reached again inside itself hashes as how many structs up it is
+pub(crate) fn struct_hash(name: &'static str, max_binaries: impl FnOnce() -> BinaryChunk) -> u64 {
+    let open = RECORDING.with(|recording| {
+        let recording = recording.borrow();
+        recording.iter().rposition(|open| open.name == name).map(|index| recording.len() - index)
+    });
+    if let Some(up) = open {
+        return SchemaHasher::new("recursive").write_u64(up as u64).finish()
+    }
+
+    RECORDING.with(|recording| recording.borrow_mut().push(Recording { name, fields: SchemaHasher::new("struct"), in_field: false }));
+    let _guard = RecordingGuard;
+    let layout = max_binaries();
+    let fields = RECORDING.with(|recording| recording.borrow().last().map(|top| top.fields)).unwrap();
+    fields.write_u64(layout_hash(&layout)).finish()
+}
+
+///Hash of a `max_binaries` layout alone
+pub fn layout_hash(chunk: &BinaryChunk) -> u64 {
+    write_layout(SchemaHasher::new("layout"), chunk).finish()
+}
+
+fn write_layout(hasher: SchemaHasher, chunk: &BinaryChunk) -> SchemaHasher {
+    match chunk {
+        BinaryChunk::Single(size) => hasher.write(&[1]).write_u64(*size as u64),
+        BinaryChunk::Nested(chunks) => {
+            let hasher = hasher.write(&[2]).write_u64(chunks.len() as u64);
+            chunks.iter().fold(hasher, write_layout)
+        },
+        BinaryChunk::Delimeter => hasher.write(&[3]),
+    }
+}
@@ -0,0 +1,343 @@
+//! Stateful sessions for sending the same type many times over one connection.
+//!
+//! Every `compress()` starts from scratch. A [`SessionEncoder`] and [`SessionDecoder`] pair keeps
+//! state between messages instead:
+//! - the previous message's bits: a message is sent as the bits that changed (an XOR delta), so
+//!   fixed width fields that did not change cost nothing
+//! - a string table: a string already sent is sent again as its index
+//! - an adaptive model: the gaps between changed bits are Rice coded, with the parameter following
+//!   the average gap seen so far
+//!
+//! Every message carries a sequence number. A keyframe carries the whole message and resets all
+//! state on both ends; it is sent for the first message, every `keyframe_interval` messages, when
+//! it comes out smaller than the delta, and after [`SessionEncoder::reset`]. A decoder that misses a
+//! message answers the following deltas with `SessionOutOfSync` until the next keyframe.
+//!
+//! ## Example
+//! ```rust,ignore
+//! use comprez::session::{SessionDecoder, SessionEncoder};
+//!
+//! let mut encoder = SessionEncoder::<Position>::new().keyframe_interval(1000);
+//! let mut decoder = SessionDecoder::<Position>::new();
+//!
+//! let bytes = encoder.encode(position)?.to_bytes();
+//! match decoder.decode(Compressed::from_bytes(bytes)) {
+//!     Err(DecompressError::SessionOutOfSync(_)) => ask_for_keyframe(),
+//!     ..
+//! }
+//! ```
+
+use std::{cell::RefCell, collections::HashMap, marker::PhantomData};
+
+use crate::{comprezable::{bit_width, calc_delimeter_size, check_decode_len, compress_str_literal, compress_varint, decompress_str_literal, push_bits, take_bits, Comprezable}, error::{CompressError, DecompressError}, Compressed};
+
+//strings past this many are sent in full every time
+const MAX_INTERNED: usize = 4096;
+//unary part of a Rice code before it escapes to a varint
+const MAX_QUOTIENT: u64 = 32;
+//gap model average, in 1/16ths
+const INITIAL_MEAN: u64 = 16 * 16;
+
+#[derive(Debug, Clone, Default)]
+struct InternTable {
+    indexes: HashMap<String, usize>,
+    strings: Vec<String>,
+}
+
+impl InternTable {
+    fn insert(&mut self, string: String) {
+        if self.strings.len() < MAX_INTERNED {
+            self.indexes.insert(string.clone(), self.strings.len());
+            self.strings.push(string);
+        }
+    }
+}
+
+thread_local! {
+    static STRINGS: RefCell<Option<InternTable>> = const { RefCell::new(None) };
+}
+
+//runs f with the table every String goes through, handing the table back after
+fn with_strings<R>(table: InternTable, f: impl FnOnce() -> R) -> (R, InternTable) {
+    let previous = STRINGS.with(|strings| strings.borrow_mut().replace(table));
+    let res = f();
+    let table = STRINGS.with(|strings| std::mem::replace(&mut *strings.borrow_mut(), previous));
+    (res, table.unwrap_or_default())
+}
+
+pub(crate) fn interning() -> bool {
+    STRINGS.with(|strings| strings.borrow().is_some())
+}
+
+//runs f and forgets the strings it interned, for an encoding that may be thrown away
+pub(crate) fn rollback<R>(f: impl FnOnce() -> R) -> R {
+    let mark = STRINGS.with(|strings| strings.borrow().as_ref().map(|table| table.strings.len()));
+    let res = f();
+    if let Some(mark) = mark {
+        STRINGS.with(|strings| {
+            if let Some(table) = strings.borrow_mut().as_mut() {
+                table.indexes.retain(|_, index| *index < mark);
+                table.strings.truncate(mark);
+            }
+        });
+    }
+    res
+}
+
+//flag 1 with the index of a string sent before, flag 0 with the string itself
+pub(crate) fn compress_interned(string: String) -> Compressed {
+    STRINGS.with(|strings| {
+        let mut strings = strings.borrow_mut();
+        let table = strings.as_mut().unwrap();
+        match table.indexes.get(&string) {
+            Some(&index) => Compressed::Binaries(vec![1]).combine(Compressed::Binaries(compress_varint(index as u128))),
+            None => {
+                table.insert(string.clone());
+                Compressed::Binaries(vec![0]).combine(compress_str_literal(string))
+            }
+        }
+    })
+}
+
+pub(crate) fn decompress_interned(compressed: &mut Vec<u8>) -> Result<String, DecompressError> {
+    let interned = take_bits(compressed, 1)? == 1;
+    let string = if interned {
+        let index = calc_delimeter_size(compressed, 7)? as usize;
+        STRINGS.with(|strings| strings.borrow().as_ref().unwrap().strings.get(index).cloned())
+        .ok_or(DecompressError::create(DecompressError::SessionOutOfSync(format!("string {} was never sent", index))))?
+    } else {
+        decompress_str_literal(compressed)?
+    };
+
+    if !interned {
+        STRINGS.with(|strings| strings.borrow_mut().as_mut().unwrap().insert(string.clone()));
+    }
+    Ok(string)
+}
+
+//Rice parameter from the running average of the gaps between changed bits
+#[derive(Debug, Clone, Copy)]
+struct GapModel {
+    mean: u64,
+}
+
+impl Default for GapModel {
+    fn default() -> Self {
+        GapModel { mean: INITIAL_MEAN }
+    }
+}
+
+impl GapModel {
+    fn rice_bits(&self) -> usize {
+        bit_width((self.mean / 16) as u128).saturating_sub(1)
+    }
+
+    fn update(&mut self, gap: u64) {
+        let gap = gap.min(u64::MAX / 32) * 16;
+        self.mean = self.mean - self.mean / 8 + gap / 8;
+    }
+
+    fn encode(&mut self, gap: u64, res: &mut Vec<u8>) {
+        let k = self.rice_bits();
+        let quotient = gap >> k;
+        if quotient < MAX_QUOTIENT {
+            res.extend((0 .. quotient).map(|_| 1));
+            res.push(0);
+            push_bits(res, (gap & ((1 << k) - 1)) as u128, k);
+        } else {
+            res.extend((0 .. MAX_QUOTIENT).map(|_| 1));
+            res.extend(compress_varint(gap as u128));
+        }
+        self.update(gap);
+    }
+
+    fn decode(&mut self, compressed: &mut Vec<u8>) -> Result<u64, DecompressError> {
+        let k = self.rice_bits();
+        let mut quotient = 0;
+        while quotient < MAX_QUOTIENT && take_bits(compressed, 1)? == 1 {
+            quotient += 1;
+        }
+        let gap = if quotient < MAX_QUOTIENT {
+            (quotient << k) | take_bits(compressed, k)? as u64
+        } else {
+            calc_delimeter_size(compressed, 7)? as u64
+        };
+        self.update(gap);
+        Ok(gap)
+    }
+}
+
+//state both ends hold after the same message
+#[derive(Debug, Clone, Default)]
+struct SessionState {
+    previous: Vec<u8>,
+    strings: InternTable,
+    model: GapModel,
+}
+
+///Compresses a stream of values against the ones sent before
+#[derive(Debug)]
+pub struct SessionEncoder<T> {
+    seq: u64,
+    state: Option<SessionState>,
+    keyframe_interval: Option<u64>,
+    since_keyframe: u64,
+    _marker: PhantomData<T>,
+}
+
+impl<T> Default for SessionEncoder<T> {
+    fn default() -> Self {
+        SessionEncoder { seq: 0, state: None, keyframe_interval: None, since_keyframe: 0, _marker: PhantomData }
+    }
+}
+
+impl<T: Comprezable + Clone> SessionEncoder<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    ///Sends a keyframe at least every `interval` messages
+    pub fn keyframe_interval(mut self, interval: u64) -> Self {
+        self.keyframe_interval = Some(interval.max(1));
+        self
+    }
+
+    ///Drops all state, the next message is a keyframe
+    pub fn reset(&mut self) {
+        self.state = None;
+    }
+
+    //sequence number, kind (0 keyframe, 1 delta), then the message
+    pub fn encode(&mut self, value: T) -> Result<Compressed, CompressError> {
+        let due = self.keyframe_interval.is_some_and(|interval| self.since_keyframe + 1 >= interval);
+        let delta = match self.state.take() {
+            Some(state) if !due => Some(encode_delta(value.clone(), state)?),
+            _ => None,
+        };
+        let (keyframe, keyframe_state) = encode_keyframe(value)?;
+
+        let mut res = compress_varint(self.seq as u128);
+        let state = match delta {
+            Some((delta, state)) if delta.len() < keyframe.len() => {
+                res.push(1);
+                res.extend(delta);
+                self.since_keyframe += 1;
+                state
+            },
+            _ => {
+                res.push(0);
+                res.extend(keyframe);
+                self.since_keyframe = 0;
+                keyframe_state
+            },
+        };
+
+        self.state = Some(state);
+        self.seq += 1;
+        Ok(Compressed::Binaries(res))
+    }
+}
+
+fn pack<T: Comprezable>(value: T, strings: InternTable) -> Result<(Vec<u8>, InternTable), CompressError> {
+    let (packed, strings) = with_strings(strings, || value.compress());
+    Ok((packed?.to_binaries(), strings))
+}
+
+//bit length, then the bits
+fn encode_keyframe<T: Comprezable>(value: T) -> Result<(Vec<u8>, SessionState), CompressError> {
+    let (packed, strings) = pack(value, InternTable::default())?;
+    let mut res = compress_varint(packed.len() as u128);
+    res.extend(packed.iter());
+    Ok((res, SessionState { previous: packed, strings, model: GapModel::default() }))
+}
+
+//bit length, changed bit count, then the gaps between changed bits
+fn encode_delta<T: Comprezable>(value: T, state: SessionState) -> Result<(Vec<u8>, SessionState), CompressError> {
+    let (packed, strings) = pack(value, state.strings)?;
+    let mut model = state.model;
+
+    let changed = (0 .. packed.len())
+    .filter(|&i| packed[i] != state.previous.get(i).copied().unwrap_or(0))
+    .collect::<Vec<usize>>();
+
+    let mut res = compress_varint(packed.len() as u128);
+    res.extend(compress_varint(changed.len() as u128));
+    let mut next = 0;
+    for position in changed {
+        model.encode((position - next) as u64, &mut res);
+        next = position + 1;
+    }
+    Ok((res, SessionState { previous: packed, strings, model }))
+}
+
+///Decompresses the messages of one `SessionEncoder`, in order
+#[derive(Debug)]
+pub struct SessionDecoder<T> {
+    expected_seq: u64,
+    state: Option<SessionState>,
+    _marker: PhantomData<T>,
+}
+
+impl<T> Default for SessionDecoder<T> {
+    fn default() -> Self {
+        SessionDecoder { expected_seq: 0, state: None, _marker: PhantomData }
+    }
+}
+
+impl<T: Comprezable> SessionDecoder<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    ///Drops all state, only a keyframe decodes after this
+    pub fn reset(&mut self) {
+        self.state = None;
+    }
+
+    ///Errors with `SessionOutOfSync` on a delta that does not follow the last decoded message.
+    ///Any failed message leaves the decoder waiting for a keyframe
+    pub fn decode(&mut self, compressed: Compressed) -> Result<T, DecompressError> {
+        let mut binaries = compressed.to_binaries();
+        let seq = calc_delimeter_size(&mut binaries, 7)? as u64;
+        let keyframe = take_bits(&mut binaries, 1)? == 0;
+
+        let state = match self.state.take() {
+            _ if keyframe => SessionState::default(),
+            Some(state) if seq == self.expected_seq => state,
+            Some(_) => return Err(DecompressError::create(DecompressError::SessionOutOfSync(format!("message {}, expected {}", seq, self.expected_seq)))),
+            None => return Err(DecompressError::create(DecompressError::SessionOutOfSync(format!("message {} is a delta with no keyframe before it", seq)))),
+        };
+
+        let (value, state) = decode_message(&mut binaries, keyframe, state)?;
+        self.state = Some(state);
+        self.expected_seq = seq + 1;
+        Ok(value)
+    }
+}
+
+fn decode_message<T: Comprezable>(binaries: &mut Vec<u8>, keyframe: bool, state: SessionState) -> Result<(T, SessionState), DecompressError> {
+    let len = calc_delimeter_size(binaries, 7)?;
+    let mut model = state.model;
+    let packed = if keyframe {
+        if (binaries.len() as u128) < len {
+            return Err(DecompressError::create(DecompressError::WrongBytesLength(format!("keyframe of {} bits is out of bound", len))))
+        }
+        binaries.drain( .. len as usize).collect::<Vec<u8>>()
+    } else {
+        //bits past the previous message are zeros unless changed, so a delta can ask for any length
+        let mut packed = state.previous;
+        packed.resize(check_decode_len(len)?, 0);
+        let count = calc_delimeter_size(binaries, 7)?;
+        let mut next = 0u64;
+        for _ in 0 .. count {
+            let position = next.saturating_add(model.decode(binaries)?);
+            let bit = packed.get_mut(position as usize).ok_or(DecompressError::create(DecompressError::WrongBytesLength(format!("changed bit {} in a message of {}", position, len))))?;
+            *bit ^= 1;
+            next = position + 1;
+        }
+        packed
+    };
+
+    let (value, strings) = with_strings(state.strings, || T::decompress(Compressed::Binaries(packed.clone())));
+    Ok((value?, SessionState { previous: packed, strings, model }))
+}
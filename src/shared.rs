@@ -0,0 +1,153 @@
+//! Deduplication of shared `Rc`/`Arc` pointers.
+//!
+//! By default every `Rc`/`Arc` is compressed as its own copy of the value.
+//! Compressing with [`compress_shared`] writes each distinct pointer once and
+//! refers back to it by index afterwards, [`decompress_shared`] rebuilds the same sharing.
+//!
+//! ```rust,ignore
+//! use std::rc::Rc;
+//! use comprez::shared::{compress_shared, decompress_shared};
+//!
+//! let config = Rc::new(Config { .. });
+//! let data = MyStruct { configs: vec![config.clone(), config.clone()] };
+//!
+//! let compressed = compress_shared(data).unwrap();
+//! let decompressed: MyStruct = decompress_shared(compressed).unwrap();
+//! assert!(Rc::ptr_eq(&decompressed.configs[0], &decompressed.configs[1]));
+//! ```
+
+use std::{any::Any, cell::RefCell, collections::HashMap};
+
+use crate::{comprezable::{calc_delimeter_size, compress_varint, take_bits, Comprezable}, error::{CompressError, DecompressError}, Compressed};
+
+struct EncodeTable {
+    indexes: HashMap<usize, usize>,
+    //keeps every pointer alive so an address cannot be reused while compressing
+    keep_alive: Vec<Box<dyn Any>>,
+}
+
+thread_local! {
+    static ENCODE_TABLE: RefCell<Option<EncodeTable>> = const { RefCell::new(None) };
+    static DECODE_TABLE: RefCell<Option<Vec<Box<dyn Any>>>> = const { RefCell::new(None) };
+}
+
+struct EncodeScope(Option<EncodeTable>);
+
+impl Drop for EncodeScope {
+    fn drop(&mut self) {
+        let previous = self.0.take();
+        ENCODE_TABLE.with(|table| *table.borrow_mut() = previous);
+    }
+}
+
+struct DecodeScope(Option<Vec<Box<dyn Any>>>);
+
+impl Drop for DecodeScope {
+    fn drop(&mut self) {
+        let previous = self.0.take();
+        DECODE_TABLE.with(|table| *table.borrow_mut() = previous);
+    }
+}
+
+///Compress with every distinct Rc/Arc written once, later occurrences become back-references
+pub fn compress_shared<T: Comprezable>(value: T) -> Result<Compressed, CompressError> {
+    let table = EncodeTable { indexes: HashMap::new(), keep_alive: vec![] };
+    let previous = ENCODE_TABLE.with(|current| current.borrow_mut().replace(table));
+    let _scope = EncodeScope(previous);
+    value.compress()
+}
+
+///Decompress a payload created by `compress_shared`, restoring the shared Rc/Arc
+pub fn decompress_shared<T: Comprezable>(compressed: Compressed) -> Result<T, DecompressError> {
+    let previous = DECODE_TABLE.with(|current| current.borrow_mut().replace(vec![]));
+    let _scope = DecodeScope(previous);
+    T::decompress(compressed)
+}
+
+pub(crate) fn sharing() -> bool {
+    ENCODE_TABLE.with(|table| table.borrow().is_some())
+}
+
+//runs f and forgets the pointers it registered, for an encoding that may be thrown away
+pub(crate) fn rollback<R>(f: impl FnOnce() -> R) -> R {
+    let mark = ENCODE_TABLE.with(|table| table.borrow().as_ref().map(|table| table.keep_alive.len()));
+    let res = f();
+    if let Some(mark) = mark {
+        ENCODE_TABLE.with(|table| {
+            if let Some(table) = table.borrow_mut().as_mut() {
+                table.indexes.retain(|_, index| *index < mark);
+                table.keep_alive.truncate(mark);
+            }
+        });
+    }
+    res
+}
+
+//flag 1 + index for a pointer seen before, flag 0 + the value otherwise
+pub(crate) fn compress_pointer<P: Clone + 'static>(
+    pointer: P,
+    address: usize,
+    max_num: Option<u128>,
+    compress: impl FnOnce(P, Option<u128>) -> Result<Compressed, CompressError>,
+) -> Result<Compressed, CompressError> {
+    let seen = ENCODE_TABLE.with(|table| {
+        table.borrow().as_ref().map(|table| table.indexes.get(&address).copied())
+    });
+
+    match seen {
+        None => compress(pointer, max_num),
+        Some(Some(index)) => {
+            let mut res = vec![1];
+            res.extend(compress_varint(index as u128));
+            Ok(Compressed::Binaries(res))
+        },
+        Some(None) => {
+            let keep_alive = pointer.clone();
+            let compressed = compress(pointer, max_num)?;
+            //registered after the value so nested pointers get their index first, same as the decoder
+            ENCODE_TABLE.with(|table| {
+                if let Some(table) = table.borrow_mut().as_mut() {
+                    let index = table.keep_alive.len();
+                    table.indexes.insert(address, index);
+                    table.keep_alive.push(Box::new(keep_alive));
+                }
+            });
+            Ok(Compressed::Binaries(vec![0]).combine(compressed))
+        }
+    }
+}
+
+pub(crate) fn decompress_pointer<P: Clone + 'static>(
+    compressed: &mut Vec<u8>,
+    bit_size: Option<usize>,
+    decompress: impl FnOnce(&mut Vec<u8>, Option<usize>) -> Result<P, DecompressError>,
+) -> Result<P, DecompressError> {
+    let active = DECODE_TABLE.with(|table| table.borrow().is_some());
+    if !active {
+        return decompress(compressed, bit_size)
+    }
+
+    match take_bits(compressed, 1)? {
+        1 => {
+            let index = calc_delimeter_size(compressed, 7)? as usize;
+            DECODE_TABLE.with(|table| {
+                let table = table.borrow();
+                let known = table.as_ref().map(|table| table.len()).unwrap_or(0);
+                table.as_ref()
+                .and_then(|table| table.get(index))
+                .and_then(|pointer| pointer.downcast_ref::<P>())
+                .cloned()
+                .ok_or(DecompressError::create(DecompressError::SharedRefErr(format!("index: {}, known: {}", index, known))))
+            })
+        },
+        _ => {
+            let pointer = decompress(compressed, bit_size)?;
+            DECODE_TABLE.with(|table| {
+                if let Some(table) = table.borrow_mut().as_mut() {
+                    table.push(Box::new(pointer.clone()));
+                }
+            });
+            Ok(pointer)
+        }
+    }
+}
@@ -0,0 +1,106 @@
+//! Sparse encoding for Vec that is mostly default values.
+//!
+//! Only the length, the gaps between non-default entries and those entries' values are written.
+//! One flag bit falls back to the plain Vec encoding whenever that comes out smaller.
+//!
+//! ## Example
+//! ```rust,ignore
+//! use comprez::sparse::Sparse;
+//!
+//! #[derive(Comprezable, Debug)]
+//! struct Features {
+//!     #[maxNum=1000]
+//!     weights: Sparse<u16>,
+//! }
+//! ```
+
+use std::fmt::Debug;
+
+use crate::{comprezable::{calc_delimeter_size, check_decode_len, compress_elements, compress_varint, decompress_elements, encode_smallest, nested_decode, nested_max_binaries, take_bits, Comprezable}, error::{CompressError, DecompressError}, schema::{record_field, SchemaHasher}, BinaryChunk, Compressed};
+
+///Vec stored as the positions and values of its non-default entries, or dense when that is smaller
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub struct Sparse<T>(pub Vec<T>);
+
+impl<T: Comprezable + Default + PartialEq + Clone + Debug> Comprezable for Sparse<T> {
+    fn compress(self) -> Result<Compressed, CompressError> {
+        Err(CompressError::create(CompressError::DataNoSupported(String::new())))
+    }
+
+    fn compress_to_binaries(self, max_num: Option<u128>) -> Result<Compressed, CompressError> {
+        //flag 0 dense, 1 sparse, dense on a tie
+        let (flag, binaries) = encode_smallest(&[0, 1], |flag| match flag {
+            1 => compress_sparse(&self.0, max_num).map(|sparse| sparse.to_binaries()),
+            _ => self.0.clone().compress_to_binaries(max_num).map(|dense| dense.to_binaries()),
+        })?;
+        Ok(Compressed::Binaries(vec![flag]).combine(Compressed::Binaries(binaries)))
+    }
+
+    fn max_binaries(max_num: Option<u128>) -> BinaryChunk {
+        let _field = record_field::<Self>(max_num);
+        nested_max_binaries::<T>(max_num)
+    }
+
+    fn schema_hash(max_num: Option<u128>) -> u64 {
+        SchemaHasher::new("Sparse").field::<T>(max_num).finish()
+    }
+
+    fn decompress(_compressed: Compressed) -> Result<Self, DecompressError> where Self: Sized {
+        Err(DecompressError::create(DecompressError::PrimitiveDataErr(String::new())))
+    }
+
+    fn decompress_from_binaries(compressed: &mut Vec<u8>, bit_size: Option<usize>) -> Result<Self, DecompressError> where Self:Sized {
+        match take_bits(compressed, 1)? {
+            1 => nested_decode(|| decompress_sparse(compressed, bit_size)).map(Sparse),
+            _ => Vec::<T>::decompress_from_binaries(compressed, bit_size).map(Sparse),
+        }
+    }
+}
+
+//length, non-default count, the gap before each non-default entry, then their values
+fn compress_sparse<T: Comprezable + Default + PartialEq + Clone>(elements: &[T], max_num: Option<u128>) -> Result<Compressed, CompressError> {
+    let default = T::default();
+    let entries = elements.iter().enumerate()
+    .filter(|(_, element)| **element != default)
+    .collect::<Vec<(usize, &T)>>();
+
+    let mut res = compress_varint(elements.len() as u128);
+    res.extend(compress_varint(entries.len() as u128));
+
+    let mut next = 0;
+    for (position, _) in entries.iter() {
+        res.extend(compress_varint((position - next) as u128));
+        next = position + 1;
+    }
+
+    let values = entries.into_iter().map(|(_, element)| element.clone()).collect::<Vec<T>>();
+    Ok(Compressed::Binaries(res).combine(compress_elements(values, max_num)?))
+}
+
+fn decompress_sparse<T: Comprezable + Default + Clone>(compressed: &mut Vec<u8>, bit_size: Option<usize>) -> Result<Vec<T>, DecompressError> {
+    //trailing defaults cost nothing, so only the limit keeps a hostile length from allocating
+    let len = check_decode_len(calc_delimeter_size(compressed, 7)?)? as u128;
+    let count = calc_delimeter_size(compressed, 7)?;
+    if count > len {
+        return Err(DecompressError::create(DecompressError::WrongBytesLength(format!("{} entries in a vector of {}", count, len))))
+    }
+
+    let mut positions = vec![];
+    let mut next: u128 = 0;
+    for _ in 0 .. count {
+        let position = next.checked_add(calc_delimeter_size(compressed, 7)?).ok_or(DecompressError::create(DecompressError::WrongBytesLength(String::from("entry gap overflows u128"))))?;
+        if position >= len {
+            return Err(DecompressError::create(DecompressError::WrongBytesLength(format!("entry at {} in a vector of {}", position, len))))
+        }
+        positions.push(position as usize);
+        next = position + 1;
+    }
+
+    let values: Vec<T> = decompress_elements(compressed, count, bit_size)?;
+
+    let mut res = vec![T::default(); len as usize];
+    for (position, value) in positions.into_iter().zip(values) {
+        res[position] = value;
+    }
+    Ok(res)
+}
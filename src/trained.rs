@@ -0,0 +1,170 @@
+//! Trained dictionaries for many small, similar `Vec<u8>` payloads.
+//!
+//! LZ4 needs repetition inside a buffer, and a single small JSON blob has little of it.
+//! [`TrainedDict::train`] collects the byte sequences that keep recurring across sample payloads,
+//! and every `Vec<u8>` compressed inside [`with_dictionary`] is then matched against them as well.
+//!
+//! The dictionary id is written in front of every such vector. Decoding has to run inside
+//! [`with_dictionary`] with the same dictionary, anything else is a `DictionaryMismatch`.
+//! Only the LZ4 codec uses the dictionary; the others ignore it.
+//!
+//! ## Example
+//! ```rust,ignore
+//! use comprez::trained::{with_dictionary, TrainedDict};
+//!
+//! let dictionary = TrainedDict::train(&samples, 16 * 1024);
+//! std::fs::write("events.dict", dictionary.as_bytes())?;
+//!
+//! let compressed = with_dictionary(&dictionary, || event.compress())?;
+//! let decompressed = with_dictionary(&dictionary, || Event::decompress(compressed))?;
+//! ```
+
+use std::{cell::RefCell, cmp::Reverse, collections::{HashMap, HashSet}, sync::Arc};
+
+use crate::{codec::check_prepended_size, error::DecompressError};
+
+///Largest dictionary LZ4 can reach back into
+pub const MAX_DICTIONARY_SIZE: usize = 64 * 1024 - 1;
+
+//length of the byte sequences counted across samples
+const GRAM_LEN: usize = 8;
+//length of the pieces the dictionary is assembled from
+const SEGMENT_LEN: usize = 32;
+const ID_BYTES: usize = 4;
+
+///Byte sequences shared by many payloads, used as a prefix LZ4 can match against
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TrainedDict {
+    bytes: Arc<[u8]>,
+    id: u32,
+}
+
+impl TrainedDict {
+    ///Picks the segments of the samples whose 8-byte sequences recur in the most other samples,
+    ///until `max_size` bytes (at most [`MAX_DICTIONARY_SIZE`]). The most common end up last, closest to the data.
+    pub fn train(samples: &[Vec<u8>], max_size: usize) -> Self {
+        let max_size = max_size.min(MAX_DICTIONARY_SIZE);
+
+        //in how many samples each sequence occurs
+        let mut frequencies: HashMap<&[u8], usize> = HashMap::new();
+        for sample in samples {
+            let grams = sample.windows(GRAM_LEN).collect::<HashSet<&[u8]>>();
+            for gram in grams {
+                *frequencies.entry(gram).or_default() += 1;
+            }
+        }
+
+        let score = |segment: &[u8], covered: &HashSet<&[u8]>| -> usize {
+            segment.windows(GRAM_LEN)
+            .filter(|gram| !covered.contains(gram))
+            .map(|gram| frequencies.get(gram).copied().unwrap_or(0).saturating_sub(1))
+            .sum()
+        };
+
+        let empty = HashSet::new();
+        let mut segments = samples.iter()
+        .flat_map(|sample| (0 .. sample.len()).step_by(SEGMENT_LEN / 2).map(move |start| &sample[start .. sample.len().min(start + SEGMENT_LEN)]))
+        .map(|segment| (score(segment, &empty), segment))
+        .filter(|(score, _)| *score > 0)
+        .collect::<Vec<(usize, &[u8])>>();
+        segments.sort_by_key(|(score, _)| Reverse(*score));
+
+        //best first, skipping what the chosen segments already cover
+        let mut covered: HashSet<&[u8]> = HashSet::new();
+        let mut chosen: Vec<&[u8]> = vec![];
+        let mut size = 0;
+        for (_, segment) in segments {
+            if size >= max_size {
+                break
+            }
+            if score(segment, &covered) == 0 {
+                continue
+            }
+            covered.extend(segment.windows(GRAM_LEN));
+            chosen.push(segment);
+            size += segment.len();
+        }
+
+        let mut bytes = chosen.into_iter().rev().flatten().copied().collect::<Vec<u8>>();
+        if bytes.len() > max_size {
+            bytes.drain( .. bytes.len() - max_size);
+        }
+        TrainedDict::from_bytes(bytes)
+    }
+
+    ///Loads a dictionary saved with [`TrainedDict::as_bytes`], keeping the last [`MAX_DICTIONARY_SIZE`] bytes
+    pub fn from_bytes(mut bytes: Vec<u8>) -> Self {
+        if bytes.len() > MAX_DICTIONARY_SIZE {
+            bytes.drain( .. bytes.len() - MAX_DICTIONARY_SIZE);
+        }
+        let id = fnv1a(&bytes);
+        TrainedDict { bytes: bytes.into(), id }
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    ///Hash of the dictionary bytes, written with every vector it compresses
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    //dictionary id, then the LZ4 block
+    pub(crate) fn encode(&self, bytes: &[u8]) -> Vec<u8> {
+        let mut res = self.id.to_le_bytes().to_vec();
+        res.extend(lz4_flex::block::compress_prepend_size_with_dict(bytes, &self.bytes));
+        res
+    }
+
+    pub(crate) fn decode(&self, bytes: &[u8]) -> Result<Vec<u8>, DecompressError> {
+        if bytes.len() < ID_BYTES {
+            return Err(DecompressError::create(DecompressError::WrongBytesLength(format!("{} bytes for a dictionary id", bytes.len()))))
+        }
+        let (id, block) = bytes.split_at(ID_BYTES);
+        let id = u32::from_le_bytes(id.try_into().unwrap());
+        if id != self.id {
+            return Err(DecompressError::create(DecompressError::DictionaryMismatch(format!("written with {:08x}, decoding with {:08x}", id, self.id))))
+        }
+        check_prepended_size(block)?;
+        lz4_flex::block::decompress_size_prepended_with_dict(block, &self.bytes).map_err(|err| {
+            DecompressError::create(DecompressError::FromBytesErr(err.to_string()))
+        })
+    }
+}
+
+fn fnv1a(bytes: &[u8]) -> u32 {
+    bytes.iter().fold(0x811c9dc5, |hash, &byte| (hash ^ byte as u32).wrapping_mul(0x01000193))
+}
+
+thread_local! {
+    static ACTIVE: RefCell<Option<TrainedDict>> = const { RefCell::new(None) };
+}
+
+///The dictionary `with_dictionary` set on this thread
+pub fn active_dictionary() -> Option<TrainedDict> {
+    ACTIVE.with(|active| active.borrow().clone())
+}
+
+struct DictionaryGuard(Option<TrainedDict>);
+
+impl Drop for DictionaryGuard {
+    fn drop(&mut self) {
+        let previous = self.0.take();
+        ACTIVE.with(|active| *active.borrow_mut() = previous);
+    }
+}
+
+///Runs `f` (a `compress()` or a `decompress()`) with `dictionary` in use for every LZ4 coded `Vec<u8>`
+pub fn with_dictionary<R>(dictionary: &TrainedDict, f: impl FnOnce() -> R) -> R {
+    let _guard = DictionaryGuard(ACTIVE.with(|active| active.borrow_mut().replace(dictionary.clone())));
+    f()
+}
+
+///Decodes a vector written with a dictionary, errors when none or another one is active
+pub(crate) fn decode_with_active(bytes: &[u8]) -> Result<Vec<u8>, DecompressError> {
+    match active_dictionary() {
+        Some(dictionary) => dictionary.decode(bytes),
+        None => Err(DecompressError::create(DecompressError::DictionaryMismatch(String::from("written with a dictionary, decoding without one")))),
+    }
+}
@@ -0,0 +1,348 @@
+//! Versioned structs, for payloads that must still decode after the struct changed.
+//!
+//! A plain struct is read back with the layout of the current code, so adding a field or raising a
+//! `maxNum` makes every stored payload undecodable. A [`Versioned`] struct is written as its version
+//! and one length-prefixed slot per field instead. [`Versioned::fields`] lists a [`FieldSpec`] per
+//! field ever written, in the order they were introduced:
+//! - a new field is listed with `FieldSpec::since(version)`; payloads older than that have no slot
+//!   for it and it is filled with its default
+//! - a removed field stays listed, marked `.retired(version)`; its slot is skipped when reading and
+//!   left empty when writing
+//! - a fixed width integer is read with the width of its slot, so raising its `maxNum` keeps older
+//!   payloads readable
+//! - any other field, such as a nested struct or a collection, has no width of its own in the slot and
+//!   must keep the types and `maxNum` bounds inside it. A slot that then does not decode to exactly its
+//!   length fails with `SchemaMismatch`; such a change goes in a new field, or through a migration
+//!
+//! New fields go at the end of the list. Payloads of a newer version then also decode with older
+//! code: slots it does not know are skipped and retired ones read as defaults.
+//!
+//! Changes defaults cannot cover, such as a split field, new units or remapped enum values, go
+//! through [`Versioned::migrations`]: each step reads payloads up to an older version as that older
+//! type, then converts it with `From`. Older types list their own steps, so a payload runs the
+//! whole chain up to the current type.
+//!
+//! [`compress_versioned`] and [`decompress_versioned`] handle a whole message, [`Evolving`] a field.
+//!
+//! ## Example
+//! ```rust,ignore
+//! use comprez::versioned::{FieldSpec, Migrations, Versioned, VersionedReader, VersionedWriter};
+//!
+//! #[derive(Comprezable, Debug, Clone)]
+//! struct Profile {
+//!     #[maxNum=100000] //was 1000 in version 1
+//!     score: u32,
+//!     name: String,
+//!     #[maxNum=100] //since version 2
+//!     level: u16,
+//! }
+//!
+//! impl Versioned for Profile {
+//!     const VERSION: u32 = 2;
+//!
+//!     fn fields() -> Vec<FieldSpec> {
+//!         vec![FieldSpec::since(1), FieldSpec::since(1), FieldSpec::since(1).retired(2), FieldSpec::since(2)]
+//!     }
+//!
+//!     fn write_fields(&self, writer: &mut VersionedWriter) -> Result<(), CompressError> {
+//!         writer.field(&self.score, Some(100000))?;
+//!         writer.field(&self.name, None)?;
+//!         writer.field(&self.level, Some(100))
+//!     }
+//!
+//!     fn read_fields(reader: &mut VersionedReader) -> Result<Self, DecompressError> {
+//!         Ok(Profile {
+//!             score: reader.field(Some(100000))?,
+//!             name: reader.field(None)?,
+//!             level: reader.field_or(Some(100), 1)?,
+//!         })
+//!     }
+//!
+//!     //version 1 kept the score in tenths
+//!     fn migrations() -> Migrations<Self> {
+//!         Migrations::new().step::<ProfileV1>()
+//!     }
+//! }
+//!
+//! impl From<ProfileV1> for Profile { .. }
+//! ```
+
+use crate::{comprezable::{calc_delimeter_size, compress_varint, Comprezable}, error::{CompressError, DecompressError}, schema::{record_field, SchemaHasher}, BinaryChunk, Compressed};
+
+///A struct written field by field into versioned slots
+pub trait Versioned: Sized {
+    ///Version this code writes, raised with every change to `fields`
+    const VERSION: u32;
+
+    ///Every field ever written, in the order they were introduced
+    fn fields() -> Vec<FieldSpec>;
+
+    ///Writes the fields that are not retired, in `fields` order
+    fn write_fields(&self, writer: &mut VersionedWriter) -> Result<(), CompressError>;
+
+    ///Reads them back in the same order
+    fn read_fields(reader: &mut VersionedReader) -> Result<Self, DecompressError>;
+
+    ///Older types that payloads of their version are read as and converted from
+    fn migrations() -> Migrations<Self> {
+        Migrations::new()
+    }
+}
+
+type MigrationStep<T> = fn(VersionedReader) -> Result<T, DecompressError>;
+
+///Migration steps of a `Versioned` type, keyed by the version of the type each one reads
+#[derive(Debug)]
+pub struct Migrations<T> {
+    steps: Vec<(u32, MigrationStep<T>)>,
+}
+
+impl<T> Default for Migrations<T> {
+    fn default() -> Self {
+        Migrations { steps: vec![] }
+    }
+}
+
+impl<T: Versioned> Migrations<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    ///Payloads up to `Old::VERSION` are read as `Old`, with its own migrations, and converted
+    pub fn step<Old: Versioned>(mut self) -> Self where T: From<Old> {
+        self.steps.push((Old::VERSION, migrate_from::<Old, T>));
+        self
+    }
+
+    //the step of the oldest type that is not older than the payload
+    fn step_for(&self, version: u32) -> Option<MigrationStep<T>> {
+        self.steps.iter()
+        .filter(|(step_version, _)| (version .. T::VERSION).contains(step_version))
+        .min_by_key(|(step_version, _)| *step_version)
+        .map(|(_, step)| *step)
+    }
+}
+
+fn migrate_from<Old: Versioned, T: From<Old>>(reader: VersionedReader) -> Result<T, DecompressError> {
+    read_versioned::<Old>(reader).map(T::from)
+}
+
+//read as T, or through the migration step that covers the payload's version
+fn read_versioned<T: Versioned>(reader: VersionedReader) -> Result<T, DecompressError> {
+    let mut reader = VersionedReader { specs: T::fields(), next: 0, ..reader };
+    match T::migrations().step_for(reader.version) {
+        Some(step) => step(reader),
+        None => T::read_fields(&mut reader),
+    }
+}
+
+///When a field was added to a `Versioned` struct, and when it was removed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct FieldSpec {
+    pub since: u32,
+    pub retired: Option<u32>,
+}
+
+impl FieldSpec {
+    pub fn since(version: u32) -> Self {
+        FieldSpec { since: version, retired: None }
+    }
+
+    ///No longer written from `version` on, older payloads still have its slot
+    pub fn retired(mut self, version: u32) -> Self {
+        self.retired = Some(version);
+        self
+    }
+
+    fn is_retired_at(&self, version: u32) -> bool {
+        self.retired.is_some_and(|retired| retired <= version)
+    }
+}
+
+///Slots being written, one per field of `Versioned::fields` present at `Versioned::VERSION`
+#[derive(Debug)]
+pub struct VersionedWriter {
+    version: u32,
+    specs: Vec<FieldSpec>,
+    next: usize,
+    slots: Vec<Vec<u8>>,
+}
+
+impl VersionedWriter {
+    pub fn new<T: Versioned>() -> Self {
+        VersionedWriter { version: T::VERSION, specs: T::fields(), next: 0, slots: vec![] }
+    }
+
+    ///The next field that is not retired, `max_num` as its attribute gives it
+    pub fn field<T: Comprezable + Clone>(&mut self, value: &T, max_num: Option<u128>) -> Result<(), CompressError> {
+        self.skip_retired();
+        if self.next >= self.specs.len() {
+            return Err(CompressError::create(CompressError::EncodeErr(format!("field {} is not in fields(), which lists {}", self.next, self.specs.len()))))
+        }
+        self.next += 1;
+        self.slots.push(value.clone().compress_to_binaries(max_num)?.to_binaries());
+        Ok(())
+    }
+
+    //retired fields keep an empty slot, so older readers stay aligned
+    fn skip_retired(&mut self) {
+        while let Some(spec) = self.specs.get(self.next) {
+            if spec.since > self.version {
+                self.next += 1;
+            } else if spec.is_retired_at(self.version) {
+                self.slots.push(vec![]);
+                self.next += 1;
+            } else {
+                break
+            }
+        }
+    }
+
+    //version, slot count, then each slot led by its bit length
+    pub fn finish(mut self) -> Result<Compressed, CompressError> {
+        self.skip_retired();
+        if self.next < self.specs.len() {
+            return Err(CompressError::create(CompressError::EncodeErr(format!("only {} of {} fields were written", self.next, self.specs.len()))))
+        }
+
+        let mut res = compress_varint(self.version as u128);
+        res.extend(compress_varint(self.slots.len() as u128));
+        for slot in self.slots {
+            res.extend(compress_varint(slot.len() as u128));
+            res.extend(slot);
+        }
+        Ok(Compressed::Binaries(res))
+    }
+}
+
+///Slots being read, field by field in `Versioned::fields` order
+#[derive(Debug)]
+pub struct VersionedReader {
+    version: u32,
+    specs: Vec<FieldSpec>,
+    next: usize,
+    slots: std::vec::IntoIter<Vec<u8>>,
+}
+
+impl VersionedReader {
+    ///Reads the version and every slot of one payload
+    pub fn new<T: Versioned>(compressed: &mut Vec<u8>) -> Result<Self, DecompressError> {
+        let version = calc_delimeter_size(compressed, 7)? as u32;
+        let count = calc_delimeter_size(compressed, 7)?;
+
+        let mut slots = vec![];
+        for _ in 0 .. count {
+            let len = calc_delimeter_size(compressed, 7)? as usize;
+            if compressed.len() < len {
+                return Err(DecompressError::create(DecompressError::WrongBytesLength(format!("slot of {} bits is out of bound", len))))
+            }
+            slots.push(compressed.drain( .. len).collect());
+        }
+        Ok(VersionedReader { version, specs: T::fields(), next: 0, slots: slots.into_iter() })
+    }
+
+    ///Version of the payload being read
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    ///The next field that is not retired, its default when the payload has none
+    pub fn field<T: Comprezable + Default>(&mut self, max_num: Option<u128>) -> Result<T, DecompressError> {
+        self.field_or(max_num, T::default())
+    }
+
+    ///The next field that is not retired, `default` when the payload has none
+    pub fn field_or<T: Comprezable>(&mut self, max_num: Option<u128>, default: T) -> Result<T, DecompressError> {
+        match self.next_slot()? {
+            Some(slot) if !slot.is_empty() => decode_slot(slot, max_num),
+            _ => Ok(default),
+        }
+    }
+
+    //the slot of the next field that is not retired, None when it is newer than the payload
+    fn next_slot(&mut self) -> Result<Option<Vec<u8>>, DecompressError> {
+        loop {
+            let spec = *self.specs.get(self.next)
+            .ok_or(DecompressError::create(DecompressError::WrongBytesLength(format!("field {} is not in fields(), which lists {}", self.next, self.specs.len()))))?;
+            self.next += 1;
+
+            let slot = match spec.since <= self.version {
+                true => Some(self.slots.next().ok_or(DecompressError::create(DecompressError::WrongBytesLength(format!("no slot for field {} in version {}", self.next - 1, self.version))))?),
+                false => None,
+            };
+            if spec.retired.is_none() {
+                return Ok(slot)
+            }
+        }
+    }
+}
+
+fn decode_slot<T: Comprezable>(mut slot: Vec<u8>, max_num: Option<u128>) -> Result<T, DecompressError> {
+    //a fixed width integer takes the width it was written with, whatever its maxNum is now
+    if T::packs_to_lanes() {
+        let len = slot.len();
+        return T::decompress_from_binaries(&mut slot, Some(len))
+    }
+
+    //nested widths are not in the slot, a changed type or maxNum inside shows as a slot not used up exactly
+    let len = slot.len();
+    let mismatch = |read: String| DecompressError::create(DecompressError::SchemaMismatch(format!("slot of {} bits, {}; a type or maxNum inside this field changed", len, read)));
+    let value = T::max_binaries(max_num).decompress(&mut slot).map_err(|err| match err {
+        DecompressError::WrongBytesLength(_) => mismatch(String::from("read past its end")),
+        err => err,
+    })?;
+    if !slot.is_empty() {
+        return Err(mismatch(format!("{} bits left over", slot.len())))
+    }
+    Ok(value)
+}
+
+///`value` as its version and field slots
+pub fn compress_versioned<T: Versioned>(value: &T) -> Result<Compressed, CompressError> {
+    let mut writer = VersionedWriter::new::<T>();
+    value.write_fields(&mut writer)?;
+    writer.finish()
+}
+
+///A payload of any version of `T`
+pub fn decompress_versioned<T: Versioned>(compressed: Compressed) -> Result<T, DecompressError> {
+    let mut binaries = compressed.to_binaries();
+    decompress_versioned_from_binaries(&mut binaries)
+}
+
+fn decompress_versioned_from_binaries<T: Versioned>(compressed: &mut Vec<u8>) -> Result<T, DecompressError> {
+    let reader = VersionedReader::new::<T>(compressed)?;
+    read_versioned(reader)
+}
+
+///Versioned struct as a field of another struct
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub struct Evolving<T>(pub T);
+
+impl<T: Versioned> Comprezable for Evolving<T> {
+    fn compress(self) -> Result<Compressed, CompressError> {
+        compress_versioned(&self.0)
+    }
+
+    fn compress_to_binaries(self, _max_num: Option<u128>) -> Result<Compressed, CompressError> {
+        compress_versioned(&self.0)
+    }
+
+    fn max_binaries(max_num: Option<u128>) -> BinaryChunk {
+        let _field = record_field::<Self>(max_num);
+        BinaryChunk::Delimeter
+    }
+
+    //the same for every version, so changing T is not a schema mismatch
+    fn schema_hash(_max_num: Option<u128>) -> u64 {
+        SchemaHasher::new("Evolving").finish()
+    }
+
+    fn decompress(compressed: Compressed) -> Result<Self, DecompressError> where Self: Sized {
+        decompress_versioned(compressed).map(Evolving)
+    }
+
+    fn decompress_from_binaries(compressed: &mut Vec<u8>, _bit_size: Option<usize>) -> Result<Self, DecompressError> where Self:Sized {
+        decompress_versioned_from_binaries(compressed).map(Evolving)
+    }
+}
@@ -156,4 +156,1381 @@ mod tests {
         println!("{}", num2);
         println!("{}", num3);
     }
+
+    #[derive(Comprezable, Debug, PartialEq, Clone)]
+    enum Tree {
+        #[maxNum=1000]
+        Leaf(u32),
+        Node(Box<Branch>),
+    }
+
+    #[derive(Comprezable, Debug, PartialEq, Clone)]
+    struct Branch {
+        left: Tree,
+        right: Tree,
+    }
+
+    fn tree_chain(depth: u32) -> Tree {
+        let mut tree = Tree::Leaf(0);
+        for i in 1 ..= depth {
+            tree = Tree::Node(Box::new(Branch { left: Tree::Leaf(i % 1000), right: tree }));
+        }
+        tree
+    }
+
+    #[test]
+    fn recursive_tree() {
+        let tree = tree_chain(50);
+        let compressed = tree.clone().compress().unwrap();
+        let decompressed = Tree::decompress(compressed).unwrap();
+        assert_eq!(tree, decompressed);
+    }
+
+    #[test]
+    fn recursive_tree_depth_limit() {
+        let compressed = tree_chain(300).compress().unwrap();
+        comprezable::set_max_decode_depth(100);
+        let decompressed = Tree::decompress(compressed);
+        comprezable::set_max_decode_depth(comprezable::DEFAULT_MAX_DECODE_DEPTH);
+        assert!(matches!(decompressed, Err(DecompressError::DepthLimitExceeded(_))));
+    }
+
+    #[derive(Comprezable, Debug, PartialEq, Clone)]
+    struct Config {
+        #[maxNum=10000]
+        threshold: u32,
+        #[maxNum=100]
+        level: u16,
+    }
+
+    #[derive(Comprezable, Debug, PartialEq, Clone)]
+    struct Batch {
+        configs: Vec<std::rc::Rc<Config>>,
+    }
+
+    #[test]
+    fn shared_rc() {
+        use std::rc::Rc;
+        use comprez::shared::{compress_shared, decompress_shared};
+
+        let first = Rc::new(Config { threshold: 9000, level: 3 });
+        let second = Rc::new(Config { threshold: 10, level: 99 });
+        let batch = Batch {
+            configs: vec![first.clone(), second.clone(), first.clone(), first, second],
+        };
+
+        let plain = batch.clone().compress().unwrap();
+        let shared = compress_shared(batch.clone()).unwrap();
+        assert!(shared.to_binaries().len() < plain.to_binaries().len());
+
+        let decompressed: Batch = decompress_shared(shared).unwrap();
+        assert_eq!(batch, decompressed);
+        assert!(Rc::ptr_eq(&decompressed.configs[0], &decompressed.configs[2]));
+        assert!(Rc::ptr_eq(&decompressed.configs[1], &decompressed.configs[4]));
+        assert!(!Rc::ptr_eq(&decompressed.configs[0], &decompressed.configs[1]));
+    }
+
+    #[derive(Comprezable, Debug, PartialEq, Clone)]
+    struct SmallNums {
+        #[maxNum=7]
+        nums: Vec<u16>,
+        #[maxNum=1000]
+        bounded: comprezable::BoundedVec<u32, 16>,
+    }
+
+    #[test]
+    fn counted_vec() {
+        //3 bit elements used to decode extra zeros from the byte padding
+        let data = SmallNums {
+            nums: vec![1, 0, 7],
+            bounded: comprezable::BoundedVec(vec![1000, 0, 3]),
+        };
+        let compressed = data.clone().compress().unwrap();
+        let decompressed = SmallNums::decompress(compressed).unwrap();
+        assert_eq!(data, decompressed);
+
+        let empty = SmallNums { nums: vec![], bounded: comprezable::BoundedVec(vec![]) };
+        let decompressed = SmallNums::decompress(empty.clone().compress().unwrap()).unwrap();
+        assert_eq!(empty, decompressed);
+
+        let too_long = SmallNums { nums: vec![], bounded: comprezable::BoundedVec(vec![0; 17]) };
+        assert!(matches!(too_long.compress(), Err(CompressError::Overflow(_))));
+    }
+
+    #[derive(Comprezable, Debug, PartialEq, Clone)]
+    struct NestedNums {
+        #[maxNum=7]
+        rows: Vec<Vec<u16>>,
+        #[maxNum=7]
+        aligned: comprezable::AlignedVec<u16>,
+    }
+
+    #[test]
+    fn bit_exact_nested_vec() {
+        let data = NestedNums {
+            rows: vec![vec![1, 2, 3]; 10],
+            aligned: comprezable::AlignedVec(vec![4, 5, 6]),
+        };
+        let compressed = data.clone().compress().unwrap();
+        //outer count, then per row a count and three 3 bit elements, then count + size + one padded byte
+        let bits = 8 + 10 * (8 + 3 * 3) + 8 + 8 + 8;
+        assert_eq!(compressed.to_bytes().len(), (bits + 7) / 8);
+
+        let decompressed = NestedNums::decompress(compressed).unwrap();
+        assert_eq!(data, decompressed);
+    }
+
+    #[derive(Comprezable, Debug, PartialEq, Clone)]
+    struct Series {
+        ids: comprez::delta::Delta<u32>,
+        timestamps: comprez::delta::DeltaOfDelta<u64>,
+        offsets: comprez::delta::Delta<i16>,
+    }
+
+    #[test]
+    fn delta_vec() {
+        use comprez::delta::{Delta, DeltaOfDelta};
+
+        let mut rng = thread_rng();
+        let timestamps = (0 .. 5000u64).map(|i| 1_700_000_000_000 + i * 1000 + rng.gen_range(0 ..= 3)).collect::<Vec<u64>>();
+        let data = Series {
+            ids: Delta((10_000 .. 15_000).collect()),
+            timestamps: DeltaOfDelta(timestamps),
+            offsets: Delta(vec![i16::MIN, i16::MAX, 0, -1, 1]),
+        };
+
+        let compressed = data.clone().compress().unwrap();
+        //41 bits per timestamp alone when stored at full width
+        assert!(compressed.to_bytes().len() < 5000 * 41 / 8 / 4);
+
+        let decompressed = Series::decompress(compressed).unwrap();
+        assert_eq!(data, decompressed);
+    }
+
+    #[derive(Comprezable, Debug, PartialEq, Clone)]
+    struct Tiles {
+        #[maxNum=300]
+        layer: comprez::rle::Rle<u16>,
+        #[maxNum=300]
+        overlay: comprez::rle::AutoRle<u16>,
+        #[maxNum=300]
+        noise: comprez::rle::AutoRle<u16>,
+    }
+
+    #[test]
+    fn rle_vec() {
+        use comprez::rle::{AutoRle, Rle};
+
+        let runs = (0 .. 4096).map(|i| (i / 512) as u16).collect::<Vec<u16>>();
+        let data = Tiles {
+            layer: Rle(runs.clone()),
+            overlay: AutoRle(runs),
+            noise: AutoRle((0 .. 300).collect()),
+        };
+
+        let compressed = data.clone().compress().unwrap();
+        //two run-length encoded layers of 8 runs, plus 300 elements of 9 bits
+        assert!(compressed.to_bytes().len() < 300 * 9 / 8 + 100);
+
+        let decompressed = Tiles::decompress(compressed).unwrap();
+        assert_eq!(data, decompressed);
+    }
+
+    #[derive(Comprezable, Debug, PartialEq, Clone)]
+    struct Readings {
+        #[maxNum=1000]
+        levels: Vec<u16>,
+        #[maxNum=60000]
+        latencies: comprez::bitpack::PFor<u32>,
+        #[maxNum=500]
+        offsets: comprez::bitpack::PFor<i64>,
+    }
+
+    #[test]
+    fn bitpacked_vec() {
+        use comprez::bitpack::PFor;
+
+        let mut rng = thread_rng();
+        let data = Readings {
+            levels: (0 .. 100_000).map(|_| rng.gen_range(0 ..= 1000)).collect(),
+            latencies: PFor((0 .. 10_000).map(|i| if i % 100 == 0 { 60000 } else { rng.gen_range(0 ..= 15) }).collect()),
+            offsets: PFor((0 .. 1000).map(|_| rng.gen_range(-500 ..= 500)).collect()),
+        };
+
+        let compressed = data.clone().compress().unwrap();
+        let decompressed = Readings::decompress(compressed).unwrap();
+        assert_eq!(data, decompressed);
+
+        //16 bits per latency at full width, the outliers are patched in
+        let latencies = data.latencies.compress_to_binaries(Some(60000)).unwrap();
+        assert!(latencies.to_binaries().len() < 10_000 * 16 / 2);
+
+        //small signed values stay narrow instead of all becoming exceptions
+        let small = (0 .. 1000).map(|_| rng.gen_range(-4 ..= 4)).collect::<Vec<i64>>();
+        let packed = PFor(small.clone()).compress_to_binaries(Some(500)).unwrap();
+        let plain = small.compress_to_binaries(Some(500)).unwrap();
+        assert!(packed.to_binaries().len() * 2 < plain.to_binaries().len());
+
+        //whole blocks are packed through words, bit for bit what the elements write one by one
+        for (max_num, len) in [(1, 129), (1000, 127), (u64::MAX as u128, 300), (u128::MAX, 257)] {
+            let values = (0 .. len).map(|_| rng.gen_range(0 ..= max_num)).collect::<Vec<u128>>();
+            let mut binaries = comprez::comprezable::compress_varint(len as u128);
+            for &value in values.iter() {
+                binaries.extend(value.compress_to_binaries(Some(max_num)).unwrap().to_binaries());
+            }
+            assert_eq!(values.clone().compress_to_binaries(Some(max_num)).unwrap().to_binaries(), binaries);
+            assert_eq!(Vec::<u128>::decompress_from_binaries(&mut binaries, u128::max_binaries(Some(max_num)).flatten().first().copied()).unwrap(), values);
+        }
+    }
+
+    #[derive(Comprezable, Debug, PartialEq, Clone)]
+    struct Features {
+        #[maxNum=1000]
+        mostly_zero: comprez::sparse::Sparse<u16>,
+        #[maxNum=1000]
+        dense: comprez::sparse::Sparse<i32>,
+    }
+
+    #[test]
+    fn sparse_vec() {
+        use comprez::sparse::Sparse;
+
+        let mut rng = thread_rng();
+        let mostly_zero = (0 .. 10_000).map(|_| if rng.gen_range(0 .. 20) == 0 { rng.gen_range(1 ..= 1000) } else { 0 }).collect::<Vec<u16>>();
+        let data = Features {
+            mostly_zero: Sparse(mostly_zero),
+            dense: Sparse((0 .. 100).map(|_| rng.gen_range(-1000 ..= 1000)).collect()),
+        };
+
+        let compressed = data.clone().compress().unwrap();
+        //10 bits per element when dense
+        assert!(compressed.to_bytes().len() < 10_000 * 10 / 8 / 4);
+
+        let decompressed = Features::decompress(compressed).unwrap();
+        assert_eq!(data, decompressed);
+    }
+
+    #[derive(Comprezable, Debug, PartialEq, Clone)]
+    struct Trade {
+        #[maxNum=1000000]
+        id: u32,
+        #[maxNum=10]
+        venue: u16,
+        #[maxNum=100000]
+        price: i64,
+    }
+
+    #[derive(Comprezable, Debug, PartialEq, Clone)]
+    struct TradeColumns {
+        id: comprez::delta::Delta<u32>,
+        #[maxNum=10]
+        venue: comprez::rle::Rle<u16>,
+        price: comprez::delta::Delta<i64>,
+    }
+
+    impl comprez::columnar::Columnar for Trade {
+        type Columns = TradeColumns;
+
+        fn to_columns(rows: Vec<Self>) -> TradeColumns {
+            use comprez::{delta::Delta, rle::Rle};
+            TradeColumns {
+                id: Delta(rows.iter().map(|row| row.id).collect()),
+                venue: Rle(rows.iter().map(|row| row.venue).collect()),
+                price: Delta(rows.iter().map(|row| row.price).collect()),
+            }
+        }
+
+        fn from_columns(columns: TradeColumns) -> Result<Vec<Self>, DecompressError> {
+            comprez::columnar::check_column_lengths(&[columns.id.0.len(), columns.venue.0.len(), columns.price.0.len()])?;
+            let rows = columns.id.0.into_iter()
+            .zip(columns.venue.0)
+            .zip(columns.price.0)
+            .map(|((id, venue), price)| Trade { id, venue, price })
+            .collect();
+            Ok(rows)
+        }
+    }
+
+    #[derive(Comprezable, Debug, PartialEq, Clone)]
+    struct TradeBatch {
+        rows: Vec<Trade>,
+    }
+
+    #[derive(Comprezable, Debug, PartialEq, Clone)]
+    struct ColumnarTradeBatch {
+        rows: comprez::columnar::Columns<Trade>,
+    }
+
+    #[test]
+    fn columnar_vec() {
+        let mut rng = thread_rng();
+        let mut price = 50_000i64;
+        let rows = (0 .. 5000).map(|i| {
+            price += rng.gen_range(-5 ..= 5);
+            Trade { id: 100_000 + i, venue: (i / 1000) as u16, price }
+        }).collect::<Vec<Trade>>();
+
+        let by_row = TradeBatch { rows: rows.clone() }.compress().unwrap();
+        let data = ColumnarTradeBatch { rows: comprez::columnar::Columns(rows) };
+        let by_column = data.clone().compress().unwrap();
+        assert!(by_column.to_bytes().len() * 4 < by_row.to_bytes().len());
+
+        let decompressed = ColumnarTradeBatch::decompress(by_column).unwrap();
+        assert_eq!(data, decompressed);
+    }
+
+    #[derive(Comprezable, Debug, PartialEq, Clone)]
+    enum Status {
+        #[maxNum=10]
+        Pending(u16),
+        #[maxNum=10]
+        Shipped(u16),
+    }
+
+    #[derive(Comprezable, Debug, PartialEq, Clone)]
+    struct Orders {
+        statuses: comprez::dictionary::Dict<Status>,
+        countries: comprez::dictionary::Dict<String>,
+        #[maxNum=100000]
+        warehouses: comprez::dictionary::Dict<u32>,
+    }
+
+    #[test]
+    fn dictionary_vec() {
+        use comprez::dictionary::Dict;
+
+        let mut rng = thread_rng();
+        let countries = ["MY", "SG", "ID", "TH"];
+        let data = Orders {
+            statuses: Dict((0 .. 2000).map(|i| if i % 3 == 0 { Status::Pending(1) } else { Status::Shipped(2) }).collect()),
+            countries: Dict((0 .. 2000).map(|_| countries[rng.gen_range(0 .. 4)].to_string()).collect()),
+            warehouses: Dict((0 .. 2000).map(|_| [12_000, 99_999][rng.gen_range(0 .. 2)]).collect()),
+        };
+
+        let compressed = data.clone().compress().unwrap();
+        //1 bit per status, 2 bits per country, 1 bit per warehouse
+        assert!(compressed.to_bytes().len() < 2000 * 4 / 8 + 50);
+
+        let decompressed = Orders::decompress(compressed).unwrap();
+        assert_eq!(data, decompressed);
+    }
+
+    #[derive(Comprezable, Debug, PartialEq, Clone)]
+    struct Level {
+        #[maxNum=500]
+        tiles: comprez::grid::Grid<u16>,
+    }
+
+    #[derive(Comprezable, Debug, PartialEq, Clone)]
+    struct NestedLevel {
+        #[maxNum=500]
+        tiles: Vec<Vec<u16>>,
+    }
+
+    #[test]
+    fn grid_tilemap() {
+        let mut rng = thread_rng();
+        //grass with a lake in the middle and a few random trees
+        let rows = (0 .. 64).map(|y| (0 .. 64).map(|x| {
+            if (20 .. 40).contains(&x) && (20 .. 40).contains(&y) { 7 }
+            else if rng.gen_range(0 .. 50) == 0 { rng.gen_range(100 ..= 500) }
+            else { 1 }
+        }).collect()).collect::<Vec<Vec<u16>>>();
+
+        let nested = NestedLevel { tiles: rows.clone() }.compress().unwrap();
+        let data = Level { tiles: comprez::grid::Grid::from_rows(rows.clone()).unwrap() };
+        let compressed = data.clone().compress().unwrap();
+        assert!(compressed.to_bytes().len() * 4 < nested.to_bytes().len());
+
+        let decompressed = Level::decompress(compressed).unwrap();
+        assert_eq!(data, decompressed);
+        assert_eq!(decompressed.tiles.into_rows(), rows);
+
+        assert!(comprez::grid::Grid::from_rows(vec![vec![1u16, 2], vec![3]]).is_err());
+    }
+
+    #[derive(Comprezable, Debug, PartialEq, Clone)]
+    struct Frame {
+        pixels: comprez::filter::Filtered,
+        audio: comprez::filter::Filtered,
+    }
+
+    #[test]
+    fn filtered_bytes() {
+        use comprez::filter::{Filter, Filtered};
+
+        let mut rng = thread_rng();
+        let (width, height) = (64, 64);
+        //smooth RGB gradients with a little noise
+        let pixels = (0 .. height).flat_map(|y| (0 .. width).flat_map(move |x| [x * 3 + y, y * 2, (x * x + y * y) / 40]))
+        .map(|channel| (channel as u8).wrapping_add(rng.gen_range(0 ..= 1)))
+        .collect::<Vec<u8>>();
+        let audio = (0 .. 4000).flat_map(|i| (((i as f64 * 0.05).sin() * 8000.0) as i16).to_le_bytes()).collect::<Vec<u8>>();
+
+        let plain = pixels.clone().compress_to_binaries(None).unwrap().to_binaries().len() + audio.clone().compress_to_binaries(None).unwrap().to_binaries().len();
+        let data = Frame {
+            pixels: Filtered { stride: 3, row_len: width * 3, ..Filtered::new(pixels.clone()) },
+            audio: Filtered { filter: Filter::Sub, stride: 2, ..Filtered::new(audio.clone()) },
+        };
+        let compressed = data.clone().compress().unwrap();
+        assert!(compressed.to_bytes().len() * 8 < plain * 3 / 4);
+
+        //Auto comes back as Auto, not as the filter it picked
+        let decompressed = Frame::decompress(compressed).unwrap();
+        assert_eq!(data, decompressed);
+        assert_eq!(decompressed.pixels.filter, Filter::Auto);
+    }
+
+    #[test]
+    fn small_and_precompressed_vec() {
+        let mut rng = thread_rng();
+        //raw: flag bit, 1 byte length, the bytes themselves
+        let small = RandomVec { data: vec![1, 2, 3, 4, 5] };
+        let compressed = small.clone().compress().unwrap();
+        assert_eq!(compressed.to_binaries().len(), 1 + 8 + 5 * 8);
+        assert_eq!(small, RandomVec::decompress(compressed).unwrap());
+
+        let mut jpeg = vec![0xFF, 0xD8, 0xFF, 0xE0];
+        jpeg.extend((0 .. 500).map(|_| rng.gen_range(0 ..= 255u8)));
+        let compressed = RandomVec { data: jpeg.clone() }.compress().unwrap();
+        assert_eq!(compressed.to_binaries().len(), 1 + 16 + jpeg.len() * 8);
+        assert_eq!(jpeg, RandomVec::decompress(compressed).unwrap().data);
+
+        let repeated = RandomVec { data: vec![7; 5000] };
+        let compressed = repeated.clone().compress().unwrap();
+        assert!(compressed.to_bytes().len() < 100);
+        assert_eq!(repeated, RandomVec::decompress(compressed).unwrap());
+    }
+
+    #[derive(Comprezable, Debug, PartialEq, Clone)]
+    struct Upload {
+        thumbnail: Vec<u8>,
+        document: comprez::codec::Coded,
+    }
+
+    #[test]
+    fn byte_codecs() {
+        use comprez::codec::{with_codec, default_codec, Codec, Coded};
+
+        let document = (0 .. 500).flat_map(|i| format!("row {}: status ok\n", i % 20).into_bytes()).collect::<Vec<u8>>();
+        let data = Upload {
+            thumbnail: document.clone(),
+            document: Coded { data: document.clone(), codec: Codec::Store },
+        };
+
+        let compressed = data.clone().compress().unwrap();
+        //thumbnail through LZ4, document stored as is
+        assert!(compressed.to_bytes().len() < document.len() + 1000);
+        assert!(compressed.to_bytes().len() > document.len());
+        assert_eq!(data, Upload::decompress(compressed).unwrap());
+
+        let stored = with_codec(Codec::Store, || data.clone().compress()).unwrap();
+        assert_eq!(default_codec(), Codec::Lz4);
+        assert!(stored.to_bytes().len() > document.len() * 2);
+        assert_eq!(data, Upload::decompress(stored).unwrap());
+
+        #[cfg(feature = "deflate")]
+        {
+            let data = Upload {
+                thumbnail: document.clone(),
+                document: Coded { data: document.clone(), codec: Codec::Deflate { level: 9 } },
+            };
+            let decompressed = Upload::decompress(data.compress().unwrap()).unwrap();
+            assert_eq!(decompressed.document.data, document);
+            assert_eq!(decompressed.document.codec, Codec::Deflate { level: comprez::codec::DEFAULT_DEFLATE_LEVEL });
+        }
+    }
+
+    #[derive(Comprezable, Debug, PartialEq, Clone)]
+    struct Event {
+        #[maxNum=100000]
+        id: u32,
+        json: Vec<u8>,
+    }
+
+    #[test]
+    fn trained_dictionary() {
+        use comprez::trained::{with_dictionary, TrainedDict};
+
+        let mut rng = thread_rng();
+        let mut json = || format!(
+            r#"{{"event":"page_view","user_id":{},"path":"/products/{}","referrer":"https://example.com/search","agent":"Mozilla/5.0 (X11; Linux x86_64)"}}"#,
+            rng.gen_range(0 .. 100000), rng.gen_range(0 .. 50),
+        ).into_bytes();
+
+        let samples = (0 .. 500).map(|_| json()).collect::<Vec<Vec<u8>>>();
+        let dictionary = TrainedDict::train(&samples, 4096);
+        assert!(dictionary.as_bytes().len() <= 4096);
+
+        let data = Event { id: 42, json: json() };
+        let plain = data.clone().compress().unwrap();
+        let trained = with_dictionary(&dictionary, || data.clone().compress()).unwrap();
+        assert!(trained.to_bytes().len() * 2 < plain.to_bytes().len());
+
+        let decompressed = with_dictionary(&dictionary, || Event::decompress(trained.clone())).unwrap();
+        assert_eq!(data, decompressed);
+
+        assert!(matches!(Event::decompress(trained.clone()), Err(DecompressError::DictionaryMismatch(_))));
+        let other = TrainedDict::from_bytes(b"another dictionary".to_vec());
+        assert!(matches!(with_dictionary(&other, || Event::decompress(trained)), Err(DecompressError::DictionaryMismatch(_))));
+    }
+
+    #[derive(Comprezable, Debug, PartialEq, Clone)]
+    struct Reading {
+        #[maxNum=100]
+        sensor: u8,
+        #[maxNum=5000]
+        value: u16,
+    }
+
+    #[derive(Comprezable, Debug, PartialEq, Clone)]
+    struct Readings {
+        items: Vec<Reading>,
+    }
+
+    #[test]
+    fn post_compression_pass() {
+        use comprez::{codec::Codec, options::Options};
+
+        let mut rng = thread_rng();
+        let pattern = (0 .. 20).map(|sensor| Reading { sensor, value: rng.gen_range(0 ..= 5000) }).collect::<Vec<Reading>>();
+        let data = Readings { items: pattern.iter().cycle().take(5000).cloned().collect() };
+        let options = Options { post_codec: Some(Codec::Lz4), ..Options::default() };
+
+        let packed = data.clone().compress().unwrap();
+        let compressed = data.clone().compress_with(&options).unwrap();
+        assert!(compressed.to_bytes().len() * 10 < packed.to_bytes().len());
+        let decompressed = Readings::decompress_with(Compressed::from_bytes(compressed.to_bytes()), &options).unwrap();
+        assert_eq!(data, decompressed);
+
+        //random values do not shrink, the pass is skipped for one flag bit
+        let data = Readings { items: (0 .. 50).map(|_| Reading { sensor: rng.gen_range(0 ..= 100), value: rng.gen_range(0 ..= 5000) }).collect() };
+        let compressed = data.clone().compress_with(&options).unwrap();
+        assert_eq!(compressed.to_binaries().len(), data.clone().compress().unwrap().to_binaries().len() + 1);
+        assert_eq!(data, Readings::decompress_with(compressed, &options).unwrap());
+    }
+
+    #[derive(Comprezable, Debug, PartialEq, Clone)]
+    struct PlayerState {
+        #[maxNum=100000]
+        x: u32,
+        #[maxNum=100000]
+        y: u32,
+        name: String,
+    }
+
+    #[test]
+    fn session_codec() {
+        use comprez::session::{SessionDecoder, SessionEncoder};
+
+        let mut rng = thread_rng();
+        let mut encoder = SessionEncoder::<PlayerState>::new().keyframe_interval(100);
+        let mut decoder = SessionDecoder::<PlayerState>::new();
+        let names = ["north-gate-guard", "south-gate-guard"];
+
+        let (mut plain, mut session) = (0, 0);
+        let mut state = PlayerState { x: 50_000, y: 50_000, name: names[0].to_string() };
+        for _ in 0 .. 300 {
+            state.x += rng.gen_range(0 ..= 3);
+            state.name = names[rng.gen_range(0 .. 2)].to_string();
+            plain += state.clone().compress().unwrap().to_bytes().len();
+
+            let bytes = encoder.encode(state.clone()).unwrap().to_bytes();
+            session += bytes.len();
+            assert_eq!(state, decoder.decode(Compressed::from_bytes(bytes)).unwrap());
+        }
+        assert!(session * 2 < plain);
+
+        //a lost message puts the decoder out of sync until a keyframe
+        let _lost = encoder.encode(state.clone()).unwrap();
+        state.y += 1;
+        assert!(matches!(decoder.decode(encoder.encode(state.clone()).unwrap()), Err(DecompressError::SessionOutOfSync(_))));
+        encoder.reset();
+        assert_eq!(state, decoder.decode(encoder.encode(state.clone()).unwrap()).unwrap());
+        state.y += 1;
+        assert_eq!(state, decoder.decode(encoder.encode(state.clone()).unwrap()).unwrap());
+    }
+
+    #[derive(Comprezable, Debug, PartialEq, Clone)]
+    struct Unit {
+        #[maxNum=1000]
+        hp: u16,
+        name: String,
+        #[maxNum=5000]
+        path: Vec<u16>,
+    }
+
+    impl comprez::diff::Diffable for Unit {
+        fn diff_to_binaries(&self, old: &Self, _max_num: Option<u128>) -> Result<Compressed, CompressError> {
+            let mut fields = comprez::diff::FieldDiffs::new();
+            fields.push(&self.hp, &old.hp, Some(1000))?;
+            fields.push(&self.name, &old.name, None)?;
+            fields.push(&self.path, &old.path, Some(5000))?;
+            Ok(fields.finish())
+        }
+
+        fn patch_from_binaries(&mut self, compressed: &mut Vec<u8>, _chunk: &BinaryChunk) -> Result<(), DecompressError> {
+            let mut fields = comprez::diff::FieldPatches::read::<Self>(compressed)?;
+            fields.apply(&mut self.hp, compressed)?;
+            fields.apply(&mut self.name, compressed)?;
+            fields.apply(&mut self.path, compressed)
+        }
+    }
+
+    #[test]
+    fn diff_and_patch() {
+        use comprez::diff::{apply_patch, compress_diff, Diffable};
+
+        let mut rng = thread_rng();
+        let old = Unit { hp: 1000, name: "archer".to_string(), path: (0 .. 500).map(|_| rng.gen_range(0 ..= 5000)).collect() };
+        let mut new = old.clone();
+        new.hp = 640;
+        new.path[42] = 7;
+        new.path.push(4999);
+
+        let patch = compress_diff(&old, &new).unwrap();
+        assert!(patch.to_bytes().len() * 20 < new.clone().compress().unwrap().to_bytes().len());
+
+        let mut synced = old.clone();
+        apply_patch(&mut synced, Compressed::from_bytes(patch.to_bytes())).unwrap();
+        assert_eq!(synced, new);
+
+        //nothing changed costs one bit per field
+        assert_eq!(compress_diff(&new, &new).unwrap().to_binaries().len(), 3);
+        new.path.truncate(10);
+        new.name = "knight".to_string();
+        apply_patch(&mut synced, compress_diff(&synced, &new).unwrap()).unwrap();
+        assert_eq!(synced, new);
+
+        //a second changed element u128::MAX past the first
+        let mut hostile = comprezable::compress_varint(3);
+        hostile.extend(comprezable::compress_varint(2));
+        hostile.extend(comprezable::compress_varint(0));
+        hostile.extend(comprezable::compress_varint(u128::MAX));
+        let patched = vec![1u32, 2, 3].patch_from_binaries(&mut hostile, &BinaryChunk::Single(2));
+        assert!(matches!(patched, Err(DecompressError::WrongBytesLength(_))));
+    }
+
+    #[derive(Comprezable, Debug, PartialEq, Clone)]
+    struct Entity {
+        #[maxNum=1000]
+        hp: u16,
+        name: String,
+        #[maxNum=100000]
+        position: Vec<u32>,
+    }
+
+    impl comprez::dirty::DirtyFields for Entity {
+        fn compress_field(&self, index: usize) -> Result<Compressed, CompressError> {
+            match index {
+                0 => self.hp.compress_to_binaries(Some(1000)),
+                1 => self.name.clone().compress_to_binaries(None),
+                _ => self.position.clone().compress_to_binaries(Some(100000)),
+            }
+        }
+
+        fn decompress_field(&mut self, index: usize, compressed: &mut Vec<u8>, chunk: &BinaryChunk) -> Result<(), DecompressError> {
+            match index {
+                0 => self.hp = chunk.decompress(compressed)?,
+                1 => self.name = chunk.decompress(compressed)?,
+                _ => self.position = chunk.decompress(compressed)?,
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn dirty_field_tracking() {
+        use comprez::dirty::{apply_dirty, apply_dirty_rows, compress_dirty_rows, Tracked};
+
+        let mut rng = thread_rng();
+        let entity = Entity { hp: 1000, name: "goblin".to_string(), position: vec![10, 20, 30] };
+        let mut remote = entity.clone();
+        let mut tracked = Tracked::new(entity);
+
+        tracked.modify(0, |entity| entity.hp -= 25);
+        tracked.modify(2, |entity| entity.position[0] = 11);
+        let update = tracked.compress_dirty().unwrap();
+        //mask, hp, position
+        assert!(update.to_binaries().len() < 3 + 10 + 80);
+        apply_dirty(&mut remote, Compressed::from_bytes(update.to_bytes())).unwrap();
+        assert_eq!(remote, *tracked);
+        assert!(!tracked.is_dirty());
+
+        let mut table = (0 .. 2000).map(|i| Tracked::new(Entity { hp: 1000, name: format!("unit-{}", i), position: vec![i, i] })).collect::<Vec<Tracked<Entity>>>();
+        let mut remote = table.iter().map(|row| (**row).clone()).collect::<Vec<Entity>>();
+        for _ in 0 .. 10 {
+            let row = rng.gen_range(0 .. 2000);
+            table[row].modify(0, |entity| entity.hp = rng.gen_range(0 ..= 1000));
+        }
+        let update = compress_dirty_rows(&mut table).unwrap();
+        assert!(update.to_bytes().len() < 60);
+        apply_dirty_rows(&mut remote, update).unwrap();
+        assert!(table.iter().zip(remote.iter()).all(|(row, remote)| **row == *remote));
+
+        //a second dirty row u128::MAX past the first
+        let mut hostile = comprezable::compress_varint(2);
+        hostile.extend(comprezable::compress_varint(0));
+        hostile.extend(comprezable::compress_varint(u128::MAX));
+        let applied = apply_dirty_rows(&mut remote, Compressed::Binaries(hostile));
+        assert!(matches!(applied, Err(DecompressError::WrongBytesLength(_))));
+    }
+
+    #[test]
+    #[should_panic(expected = "field 3 of a struct with 3 fields")]
+    fn dirty_field_out_of_range() {
+        let entity = Entity { hp: 1000, name: "goblin".to_string(), position: vec![10, 20, 30] };
+        comprez::dirty::Tracked::new(entity).modify(3, |entity| entity.hp -= 25);
+    }
+
+    #[test]
+    fn format_header() {
+        use comprez::{codec::Codec, options::{Options, FORMAT_VERSION}};
+
+        let mut rng = thread_rng();
+        let data = Readings { items: (0 .. 200).map(|_| Reading { sensor: rng.gen_range(0 ..= 100), value: rng.gen_range(0 ..= 5000) }).collect() };
+        let options = Options { post_codec: Some(Codec::Lz4), header: true, ..Options::default() };
+
+        let bytes = data.clone().compress_with(&options).unwrap().to_bytes();
+        assert_eq!(bytes[.. 3], [b'C', b'Z', FORMAT_VERSION]);
+        //the header's flags say how the message was written
+        let decompressed = Readings::decompress_with(Compressed::from_bytes(bytes.clone()), &Options { header: true, ..Options::default() }).unwrap();
+        assert_eq!(data, decompressed);
+
+        let mut newer = bytes.clone();
+        newer[2] = FORMAT_VERSION + 1;
+        assert!(matches!(Readings::decompress_with(Compressed::from_bytes(newer), &options), Err(DecompressError::UnsupportedVersion(_))));
+
+        let random = (0 .. bytes.len()).map(|_| rng.gen_range(0 ..= 255) as u8).collect::<Vec<u8>>();
+        if random[.. 2] != *b"CZ" {
+            assert!(matches!(Readings::decompress_with(Compressed::from_bytes(random), &options), Err(DecompressError::BadMagic(_))));
+        }
+    }
+
+    #[test]
+    fn checksum_trailer() {
+        use comprez::{checksum::{crc32c, xxhash64, Checksum}, options::Options};
+
+        assert_eq!(crc32c(b"123456789"), 0xE306_9283);
+        assert_eq!(xxhash64(b"abc", 0), 0x44BC_2CF5_AD77_0999);
+
+        let mut rng = thread_rng();
+        let data = Readings { items: (0 .. 200).map(|_| Reading { sensor: rng.gen_range(0 ..= 100), value: rng.gen_range(0 ..= 5000) }).collect() };
+        for checksum in [Checksum::Crc32c, Checksum::XxHash64] {
+            let options = Options { checksum: Some(checksum), header: true, ..Options::default() };
+            let bytes = data.clone().compress_with(&options).unwrap().to_bytes();
+            let decompressed = Readings::decompress_with(Compressed::from_bytes(bytes.clone()), &options).unwrap();
+            assert_eq!(data, decompressed);
+
+            //one flipped bit anywhere after the header
+            let mut corrupted = bytes.clone();
+            let index = rng.gen_range(4 .. corrupted.len());
+            corrupted[index] ^= 1 << rng.gen_range(0 .. 8);
+            assert!(matches!(Readings::decompress_with(Compressed::from_bytes(corrupted), &options), Err(DecompressError::ChecksumMismatch(_))));
+        }
+    }
+
+    #[derive(Comprezable, Debug, PartialEq, Clone)]
+    struct WideReading {
+        #[maxNum=100]
+        sensor: u8,
+        #[maxNum=100000]
+        value: u32,
+    }
+
+    #[derive(Comprezable, Debug, PartialEq, Clone)]
+    struct Label {
+        #[maxNum=1000]
+        id: u16,
+        text: String,
+    }
+
+    #[derive(Comprezable, Debug, PartialEq, Clone)]
+    struct RetypedLabel {
+        #[maxNum=900]
+        id: u64,
+        text: Vec<u8>,
+    }
+
+    #[derive(Comprezable, Debug, PartialEq, Clone)]
+    struct RaisedLabel {
+        #[maxNum=1001]
+        id: u16,
+        text: String,
+    }
+
+    #[test]
+    fn schema_fingerprint() {
+        use comprez::options::Options;
+
+        assert_eq!(Reading::schema_hash(None), Reading::schema_hash(None));
+        assert_ne!(Reading::schema_hash(None), WideReading::schema_hash(None));
+        assert_ne!(u16::schema_hash(Some(5000)), u16::schema_hash(Some(6000)));
+        assert_ne!(u16::schema_hash(Some(5000)), u32::schema_hash(Some(5000)));
+        assert_ne!(comprez::delta::Delta::<i32>::schema_hash(None), comprez::delta::Delta::<u32>::schema_hash(None));
+        assert_ne!(comprez::delta::DeltaOfDelta::<i64>::schema_hash(None), comprez::delta::DeltaOfDelta::<u64>::schema_hash(None));
+        //same bit layout, but another field type or maxNum
+        assert_ne!(Label::schema_hash(None), RetypedLabel::schema_hash(None));
+        assert_ne!(Label::schema_hash(None), RaisedLabel::schema_hash(None));
+
+        let mut rng = thread_rng();
+        let data = Reading { sensor: rng.gen_range(0 ..= 100), value: rng.gen_range(0 ..= 5000) };
+        let options = Options { schema: true, header: true, ..Options::default() };
+        let bytes = data.clone().compress_with(&options).unwrap().to_bytes();
+        assert_eq!(data, Reading::decompress_with(Compressed::from_bytes(bytes.clone()), &options).unwrap());
+        //a raised maxNum is caught instead of decoding into garbage
+        assert!(matches!(WideReading::decompress_with(Compressed::from_bytes(bytes), &options), Err(DecompressError::SchemaMismatch(_))));
+    }
+
+    #[derive(Debug, PartialEq, Clone)]
+    struct ProfileV1 {
+        score: u32,
+        name: String,
+        nickname: String,
+    }
+
+    impl comprez::versioned::Versioned for ProfileV1 {
+        const VERSION: u32 = 1;
+
+        fn fields() -> Vec<comprez::versioned::FieldSpec> {
+            use comprez::versioned::FieldSpec;
+            vec![FieldSpec::since(1), FieldSpec::since(1), FieldSpec::since(1)]
+        }
+
+        fn write_fields(&self, writer: &mut comprez::versioned::VersionedWriter) -> Result<(), CompressError> {
+            writer.field(&self.score, Some(1000))?;
+            writer.field(&self.name, None)?;
+            writer.field(&self.nickname, None)
+        }
+
+        fn read_fields(reader: &mut comprez::versioned::VersionedReader) -> Result<Self, DecompressError> {
+            Ok(ProfileV1 { score: reader.field(Some(1000))?, name: reader.field(None)?, nickname: reader.field(None)? })
+        }
+    }
+
+    //version 2 raised the score bound, retired nickname and added level
+    #[derive(Debug, PartialEq, Clone)]
+    struct Profile {
+        score: u32,
+        name: String,
+        level: u16,
+    }
+
+    impl comprez::versioned::Versioned for Profile {
+        const VERSION: u32 = 2;
+
+        fn fields() -> Vec<comprez::versioned::FieldSpec> {
+            use comprez::versioned::FieldSpec;
+            vec![FieldSpec::since(1), FieldSpec::since(1), FieldSpec::since(1).retired(2), FieldSpec::since(2)]
+        }
+
+        fn write_fields(&self, writer: &mut comprez::versioned::VersionedWriter) -> Result<(), CompressError> {
+            writer.field(&self.score, Some(100000))?;
+            writer.field(&self.name, None)?;
+            writer.field(&self.level, Some(100))
+        }
+
+        fn read_fields(reader: &mut comprez::versioned::VersionedReader) -> Result<Self, DecompressError> {
+            Ok(Profile { score: reader.field(Some(100000))?, name: reader.field(None)?, level: reader.field_or(Some(100), 1)? })
+        }
+    }
+
+    #[test]
+    fn versioned_fields() {
+        use comprez::versioned::{compress_versioned, decompress_versioned, Evolving};
+
+        let mut rng = thread_rng();
+        let old = ProfileV1 { score: rng.gen_range(0 ..= 1000), name: String::from("ada"), nickname: String::from("countess") };
+        let archived = compress_versioned(&old).unwrap().to_bytes();
+        let upgraded: Profile = decompress_versioned(Compressed::from_bytes(archived)).unwrap();
+        assert_eq!(upgraded, Profile { score: old.score, name: old.name.clone(), level: 1 });
+
+        //older code still reads the newer payload, the retired field comes back empty
+        let new = Profile { score: rng.gen_range(1001 ..= 100000), name: String::from("grace"), level: rng.gen_range(0 ..= 100) };
+        let bytes = compress_versioned(&new).unwrap().to_bytes();
+        assert_eq!(new, decompress_versioned(Compressed::from_bytes(bytes.clone())).unwrap());
+        let downgraded: ProfileV1 = decompress_versioned(Compressed::from_bytes(bytes)).unwrap();
+        assert_eq!(downgraded, ProfileV1 { score: new.score, name: new.name.clone(), nickname: String::new() });
+
+        let evolving = Evolving(new);
+        let compressed = evolving.clone().compress().unwrap();
+        assert_eq!(evolving, Evolving::decompress(compressed).unwrap());
+    }
+
+    //version 3 split the name, which default-filling cannot do
+    #[derive(Debug, PartialEq, Clone)]
+    struct ProfileV3 {
+        score: u32,
+        first_name: String,
+        last_name: String,
+        level: u16,
+    }
+
+    impl From<Profile> for ProfileV3 {
+        fn from(profile: Profile) -> Self {
+            let (first_name, last_name) = profile.name.split_once(' ').unwrap_or((&profile.name, ""));
+            ProfileV3 { score: profile.score, first_name: first_name.to_string(), last_name: last_name.to_string(), level: profile.level }
+        }
+    }
+
+    impl comprez::versioned::Versioned for ProfileV3 {
+        const VERSION: u32 = 3;
+
+        fn fields() -> Vec<comprez::versioned::FieldSpec> {
+            use comprez::versioned::FieldSpec;
+            vec![FieldSpec::since(3); 4]
+        }
+
+        fn write_fields(&self, writer: &mut comprez::versioned::VersionedWriter) -> Result<(), CompressError> {
+            writer.field(&self.score, Some(100000))?;
+            writer.field(&self.first_name, None)?;
+            writer.field(&self.last_name, None)?;
+            writer.field(&self.level, Some(100))
+        }
+
+        fn read_fields(reader: &mut comprez::versioned::VersionedReader) -> Result<Self, DecompressError> {
+            Ok(ProfileV3 { score: reader.field(Some(100000))?, first_name: reader.field(None)?, last_name: reader.field(None)?, level: reader.field(Some(100))? })
+        }
+
+        fn migrations() -> comprez::versioned::Migrations<Self> {
+            comprez::versioned::Migrations::new().step::<Profile>()
+        }
+    }
+
+    #[test]
+    fn versioned_migrations() {
+        use comprez::versioned::{compress_versioned, decompress_versioned};
+
+        let mut rng = thread_rng();
+        //version 1 is read as Profile first, filling level, then converted
+        let old = ProfileV1 { score: rng.gen_range(0 ..= 1000), name: String::from("ada lovelace"), nickname: String::new() };
+        let archived = compress_versioned(&old).unwrap().to_bytes();
+        let migrated: ProfileV3 = decompress_versioned(Compressed::from_bytes(archived)).unwrap();
+        assert_eq!(migrated, ProfileV3 { score: old.score, first_name: String::from("ada"), last_name: String::from("lovelace"), level: 1 });
+
+        let profile = Profile { score: rng.gen_range(0 ..= 100000), name: String::from("grace hopper"), level: rng.gen_range(0 ..= 100) };
+        let migrated: ProfileV3 = decompress_versioned(compress_versioned(&profile).unwrap()).unwrap();
+        assert_eq!(migrated, ProfileV3::from(profile));
+
+        let current = ProfileV3 { score: 7, first_name: String::from("alan"), last_name: String::from("turing"), level: 100 };
+        assert_eq!(current, decompress_versioned(compress_versioned(&current).unwrap()).unwrap());
+    }
+
+    #[derive(Debug, PartialEq, Clone)]
+    enum Event {
+        Spawn(u16),
+        Chat(String),
+        Despawn,
+    }
+
+    impl Comprezable for Event {
+        fn compress(self) -> Result<Compressed, CompressError> {
+            self.compress_to_binaries(None)
+        }
+
+        fn compress_to_binaries(self, _max_num: Option<u128>) -> Result<Compressed, CompressError> {
+            use comprez::framed::comprez_framed_enum_val;
+            match self {
+                Event::Spawn(id) => comprez_framed_enum_val(Some(id), 0, Some(1000)),
+                Event::Chat(text) => comprez_framed_enum_val(Some(text), 1, None),
+                Event::Despawn => comprez_framed_enum_val::<u16>(None, 2, None),
+            }
+        }
+
+        fn max_binaries(_max_num: Option<u128>) -> BinaryChunk {
+            BinaryChunk::Delimeter
+        }
+
+        fn decompress(compressed: Compressed) -> Result<Self, DecompressError> {
+            Self::decompress_from_binaries(&mut compressed.to_binaries(), None)
+        }
+
+        fn decompress_from_binaries(compressed: &mut Vec<u8>, _bit_size: Option<usize>) -> Result<Self, DecompressError> {
+            use comprez::framed::{decompress_framed_payload, read_framed_variant, UnknownVariant};
+            match read_framed_variant(compressed)? {
+                UnknownVariant { index: 0, payload } => Ok(Event::Spawn(decompress_framed_payload(payload, Some(1000))?)),
+                UnknownVariant { index: 1, payload } => Ok(Event::Chat(decompress_framed_payload(payload, None)?)),
+                UnknownVariant { index: 2, .. } => Ok(Event::Despawn),
+                unknown => Err(unknown.into_err()),
+            }
+        }
+    }
+
+    //Event as built before Chat and Despawn were added, with a fallback variant
+    #[derive(Debug, PartialEq, Clone)]
+    enum OldEvent {
+        Spawn(u16),
+        Other(comprez::framed::UnknownVariant),
+    }
+
+    impl Comprezable for OldEvent {
+        fn compress(self) -> Result<Compressed, CompressError> {
+            self.compress_to_binaries(None)
+        }
+
+        fn compress_to_binaries(self, _max_num: Option<u128>) -> Result<Compressed, CompressError> {
+            match self {
+                OldEvent::Spawn(id) => comprez::framed::comprez_framed_enum_val(Some(id), 0, Some(1000)),
+                OldEvent::Other(unknown) => Ok(unknown.to_framed()),
+            }
+        }
+
+        fn max_binaries(_max_num: Option<u128>) -> BinaryChunk {
+            BinaryChunk::Delimeter
+        }
+
+        fn decompress(compressed: Compressed) -> Result<Self, DecompressError> {
+            Self::decompress_from_binaries(&mut compressed.to_binaries(), None)
+        }
+
+        fn decompress_from_binaries(compressed: &mut Vec<u8>, _bit_size: Option<usize>) -> Result<Self, DecompressError> {
+            use comprez::framed::{decompress_framed_payload, read_framed_variant, UnknownVariant};
+            match read_framed_variant(compressed)? {
+                UnknownVariant { index: 0, payload } => Ok(OldEvent::Spawn(decompress_framed_payload(payload, Some(1000))?)),
+                unknown => Ok(OldEvent::Other(unknown)),
+            }
+        }
+    }
+
+    #[derive(Comprezable, Debug, PartialEq, Clone)]
+    struct EventLog {
+        events: Vec<Event>,
+    }
+
+    #[derive(Comprezable, Debug, PartialEq, Clone)]
+    struct OldEventLog {
+        events: Vec<OldEvent>,
+    }
+
+    #[test]
+    fn framed_enum_fallback() {
+        let mut rng = thread_rng();
+        let id = rng.gen_range(0 ..= 1000);
+        let log = EventLog { events: vec![Event::Spawn(id), Event::Chat(String::from("gg")), Event::Despawn, Event::Spawn(7)] };
+        let bytes = log.clone().compress().unwrap().to_bytes();
+
+        //the old decoder skips what it does not know and keeps it
+        let old = OldEventLog::decompress(Compressed::from_bytes(bytes.clone())).unwrap();
+        assert_eq!(old.events[0], OldEvent::Spawn(id));
+        assert!(matches!(old.events[1], OldEvent::Other(_)));
+        assert_eq!(old.events[3], OldEvent::Spawn(7));
+
+        //and sends it on unchanged
+        let forwarded = old.compress().unwrap().to_bytes();
+        assert_eq!(log, EventLog::decompress(Compressed::from_bytes(forwarded)).unwrap());
+
+        let unknown = OldEvent::Other(comprez::framed::UnknownVariant { index: 9, payload: vec![1, 0, 1] });
+        assert!(matches!(Event::decompress(unknown.compress().unwrap()), Err(DecompressError::UnknownVariantErr(_))));
+    }
+
+    #[derive(Comprezable, Debug, PartialEq, Clone)]
+    struct Node {
+        #[maxNum=1000]
+        value: u16,
+        children: Vec<Node>,
+    }
+
+    fn node_chain(depth: u16) -> Node {
+        let mut node = Node { value: 0, children: vec![] };
+        for i in 1 ..= depth {
+            node = Node { value: i % 1000, children: vec![Node { value: 7, children: vec![] }, node] };
+        }
+        node
+    }
+
+    #[test]
+    fn vec_recursive_type() {
+        //recursion through Vec stops in max_binaries instead of overflowing the stack
+        let node = node_chain(40);
+        let decompressed = Node::decompress(node.clone().compress().unwrap()).unwrap();
+        assert_eq!(node, decompressed);
+
+        let compressed = node_chain(300).compress().unwrap();
+        comprezable::set_max_decode_depth(100);
+        let decompressed = Node::decompress(compressed);
+        comprezable::set_max_decode_depth(comprezable::DEFAULT_MAX_DECODE_DEPTH);
+        assert!(matches!(decompressed, Err(DecompressError::DepthLimitExceeded(_))));
+    }
+
+    #[derive(Comprezable, Debug, PartialEq, Clone)]
+    struct SharedLayers {
+        #[maxNum=1000]
+        markers: comprez::sparse::Sparse<std::rc::Rc<u16>>,
+        #[maxNum=1000]
+        tiles: comprez::grid::Grid<std::rc::Rc<u16>>,
+    }
+
+    #[test]
+    fn shared_candidate_encodings() {
+        use std::rc::Rc;
+        use comprez::shared::{compress_shared, decompress_shared};
+
+        //Sparse and Grid try several layouts, only the one written may register pointers
+        let (empty, tree, rock) = (Rc::new(0u16), Rc::new(7u16), Rc::new(900u16));
+        let cells = (0 .. 40).map(|i| match i % 9 { 0 => tree.clone(), 4 => rock.clone(), _ => empty.clone() }).collect::<Vec<Rc<u16>>>();
+        let layers = SharedLayers {
+            markers: comprez::sparse::Sparse(cells.clone()),
+            tiles: comprez::grid::Grid { width: 8, height: 5, cells },
+        };
+
+        let decompressed: SharedLayers = decompress_shared(compress_shared(layers.clone()).unwrap()).unwrap();
+        assert_eq!(layers, decompressed);
+        assert!(Rc::ptr_eq(&decompressed.tiles.cells[0], &decompressed.tiles.cells[9]));
+    }
+
+    #[test]
+    fn rle_length_limit() {
+        use comprez::{comprezable::compress_varint, rle::Rle};
+
+        //a single run of 2^40 elements is a few bytes of input
+        let mut hostile = compress_varint(1 << 40);
+        hostile.extend(compress_varint((1 << 40) - 1));
+        hostile.extend([0; 9]);
+        let decompressed = Rle::<u16>::decompress_from_binaries(&mut hostile, Some(9));
+        assert!(matches!(decompressed, Err(DecompressError::LengthLimitExceeded(_))));
+
+        let compressed = Tiles { layer: Rle(vec![3; 1000]), overlay: Default::default(), noise: Default::default() }.compress().unwrap();
+        comprezable::set_max_decode_len(999);
+        let decompressed = Tiles::decompress(compressed);
+        comprezable::set_max_decode_len(comprezable::DEFAULT_MAX_DECODE_LEN);
+        assert!(matches!(decompressed, Err(DecompressError::LengthLimitExceeded(_))));
+
+        //a run of u128::MAX + 1 elements overflows before it is compared to the count
+        let mut hostile = compress_varint(10);
+        hostile.extend(compress_varint(u128::MAX));
+        hostile.extend([0; 9]);
+        let decompressed = Rle::<u16>::decompress_from_binaries(&mut hostile, Some(9));
+        assert!(matches!(decompressed, Err(DecompressError::WrongBytesLength(_))));
+    }
+
+    #[test]
+    fn sparse_hostile_length() {
+        use comprez::{comprezable::compress_varint, sparse::Sparse};
+
+        //sparse flag, a length of 2^50 and no entries, a few bytes asking for petabytes of defaults
+        let mut hostile = vec![1];
+        hostile.extend(compress_varint(1 << 50));
+        hostile.extend(compress_varint(0));
+        let decompressed = Sparse::<u16>::decompress_from_binaries(&mut hostile, Some(10));
+        assert!(matches!(decompressed, Err(DecompressError::LengthLimitExceeded(_))));
+
+        //a second entry u128::MAX past the first
+        let mut hostile = vec![1];
+        hostile.extend(compress_varint(10));
+        hostile.extend(compress_varint(2));
+        hostile.extend(compress_varint(0));
+        hostile.extend(compress_varint(u128::MAX));
+        let decompressed = Sparse::<u16>::decompress_from_binaries(&mut hostile, Some(10));
+        assert!(matches!(decompressed, Err(DecompressError::WrongBytesLength(_))));
+    }
+
+    #[test]
+    fn dictionary_hostile_count() {
+        use comprez::{comprezable::compress_varint, dictionary::Dict};
+
+        //one entry means 0 bit indexes, so 2^60 elements would need no further input
+        let mut hostile = compress_varint(1);
+        hostile.extend(7u16.compress_to_binaries(Some(1000)).unwrap().to_binaries());
+        hostile.extend(compress_varint(1 << 60));
+        let decompressed = Dict::<u16>::decompress_from_binaries(&mut hostile, Some(10));
+        assert!(matches!(decompressed, Err(DecompressError::LengthLimitExceeded(_))));
+    }
+
+    #[test]
+    fn grid_hostile_size() {
+        use comprez::{comprezable::compress_varint, grid::Grid};
+
+        //a 2^20 x 2^20 quadtree that is one uniform square, 2^40 cells from a few bytes
+        let mut hostile = compress_varint(1 << 20);
+        hostile.extend(compress_varint(1 << 20));
+        hostile.extend([1, 0, 1]);
+        hostile.extend([0; 10]);
+        let decompressed = Grid::<u16>::decompress_from_binaries(&mut hostile, Some(10));
+        assert!(matches!(decompressed, Err(DecompressError::LengthLimitExceeded(_))));
+
+        //a 4 x 4 grid of runs whose first run is u128::MAX + 1 cells
+        let mut hostile = compress_varint(4);
+        hostile.extend(compress_varint(4));
+        hostile.extend([0, 1, 0]);
+        hostile.extend(compress_varint(u128::MAX));
+        hostile.extend([0; 10]);
+        let decompressed = Grid::<u16>::decompress_from_binaries(&mut hostile, Some(10));
+        assert!(matches!(decompressed, Err(DecompressError::WrongBytesLength(_))));
+    }
+
+    #[derive(Comprezable, Debug, PartialEq, Clone)]
+    struct LabelLayers {
+        labels: comprez::sparse::Sparse<String>,
+        names: comprez::grid::Grid<String>,
+    }
+
+    #[test]
+    fn session_candidate_encodings() {
+        use comprez::{comprezable::compress_varint, session::{SessionDecoder, SessionEncoder}};
+
+        //Sparse and Grid try several layouts, only the one written may intern strings
+        let mut encoder = SessionEncoder::<LabelLayers>::new();
+        let mut decoder = SessionDecoder::<LabelLayers>::new();
+        for i in 0 .. 10 {
+            let cells = (0 .. 40).map(|j| match (i + j) % 9 {
+                0 => String::from("tree"),
+                4 => format!("rock-{}", i % 3),
+                _ => String::new(),
+            }).collect::<Vec<String>>();
+            let layers = LabelLayers {
+                labels: comprez::sparse::Sparse(cells.clone()),
+                names: comprez::grid::Grid { width: 8, height: 5, cells },
+            };
+            assert_eq!(layers, decoder.decode(encoder.encode(layers.clone()).unwrap()).unwrap());
+        }
+
+        //a delta asking for 2^60 bits past the previous message
+        let mut hostile = compress_varint(10);
+        hostile.push(1);
+        hostile.extend(compress_varint(1 << 60));
+        hostile.extend(compress_varint(0));
+        assert!(matches!(decoder.decode(Compressed::Binaries(hostile)), Err(DecompressError::LengthLimitExceeded(_))));
+    }
+
+    #[derive(Comprezable, Debug, PartialEq, Clone, Default)]
+    struct Waypoint {
+        #[maxNum=1000]
+        x: u16,
+        #[maxNum=1000]
+        y: u16,
+    }
+
+    //version 2 raised x inside the nested waypoint
+    #[derive(Comprezable, Debug, PartialEq, Clone, Default)]
+    struct WideWaypoint {
+        #[maxNum=100000]
+        x: u32,
+        #[maxNum=1000]
+        y: u16,
+    }
+
+    #[derive(Debug, PartialEq, Clone)]
+    struct RouteV1 {
+        stop: Waypoint,
+    }
+
+    impl comprez::versioned::Versioned for RouteV1 {
+        const VERSION: u32 = 1;
+
+        fn fields() -> Vec<comprez::versioned::FieldSpec> {
+            vec![comprez::versioned::FieldSpec::since(1)]
+        }
+
+        fn write_fields(&self, writer: &mut comprez::versioned::VersionedWriter) -> Result<(), CompressError> {
+            writer.field(&self.stop, None)
+        }
+
+        fn read_fields(reader: &mut comprez::versioned::VersionedReader) -> Result<Self, DecompressError> {
+            Ok(RouteV1 { stop: reader.field(None)? })
+        }
+    }
+
+    #[derive(Debug, PartialEq, Clone)]
+    struct Route {
+        stop: WideWaypoint,
+    }
+
+    impl comprez::versioned::Versioned for Route {
+        const VERSION: u32 = 2;
+
+        fn fields() -> Vec<comprez::versioned::FieldSpec> {
+            vec![comprez::versioned::FieldSpec::since(1)]
+        }
+
+        fn write_fields(&self, writer: &mut comprez::versioned::VersionedWriter) -> Result<(), CompressError> {
+            writer.field(&self.stop, None)
+        }
+
+        fn read_fields(reader: &mut comprez::versioned::VersionedReader) -> Result<Self, DecompressError> {
+            Ok(Route { stop: reader.field(None)? })
+        }
+    }
+
+    #[test]
+    fn versioned_nested_field() {
+        use comprez::versioned::{compress_versioned, decompress_versioned};
+
+        //only top level integers are read at their stored width, a nested change is refused instead of misread
+        let old = RouteV1 { stop: Waypoint { x: 999, y: 5 } };
+        let archived = compress_versioned(&old).unwrap();
+        assert_eq!(old, decompress_versioned(archived.clone()).unwrap());
+        assert!(matches!(decompress_versioned::<Route>(archived), Err(DecompressError::SchemaMismatch(_))));
+
+        let new = Route { stop: WideWaypoint { x: 99999, y: 5 } };
+        assert!(matches!(decompress_versioned::<RouteV1>(compress_versioned(&new).unwrap()), Err(DecompressError::SchemaMismatch(_))));
+    }
+
+    #[test]
+    fn varint_too_long() {
+        use comprez::comprezable::{calc_delimeter_size, compress_varint};
+
+        //nineteen groups of ones is 133 bits, more than a u128 holds
+        let mut hostile: Vec<u8> = [[0, 1, 1, 1, 1, 1, 1, 1]; 18].concat();
+        hostile.extend([1, 1, 1, 1, 1, 1, 1, 1]);
+        assert!(matches!(calc_delimeter_size(&mut hostile, 7), Err(DecompressError::BinariesToIntErr(_))));
+
+        //an endless run of continuation groups is cut off before it is collected
+        let mut hostile: Vec<u8> = [[0, 0, 0, 0, 0, 0, 0, 0]; 1000].concat();
+        assert!(matches!(calc_delimeter_size(&mut hostile, 7), Err(DecompressError::BinariesToIntErr(_))));
+
+        let mut max = compress_varint(u128::MAX);
+        assert_eq!(calc_delimeter_size(&mut max, 7).unwrap(), u128::MAX);
+    }
+
+    #[test]
+    fn string_hostile_length() {
+        use comprez::comprezable::compress_varint;
+
+        //a length whose bit count does not fit in u128 is rejected, not multiplied
+        let mut hostile = compress_varint(u128::MAX / 4);
+        hostile.extend([0; 64]);
+        let decompressed = String::decompress_from_binaries(&mut hostile, None);
+        assert!(matches!(decompressed, Err(DecompressError::WrongBytesLength(_))));
+    }
+
+    #[test]
+    fn aligned_vec_hostile_size() {
+        use comprez::comprezable::{compress_varint, AlignedVec};
+
+        //a byte size whose bit count does not fit in u128 is rejected, not multiplied
+        let mut hostile = compress_varint(1);
+        hostile.extend(compress_varint(u128::MAX / 4));
+        hostile.extend([0; 64]);
+        let decompressed = AlignedVec::<u16>::decompress_from_binaries(&mut hostile, Some(16));
+        assert!(matches!(decompressed, Err(DecompressError::WrongBytesLength(_))));
+    }
+
+    #[test]
+    fn codec_output_limit() {
+        use comprez::{codec::{Codec, Coded}, comprezable::compress_varint};
+
+        //stored flag and a byte length whose bit count does not fit in u128
+        let mut hostile = vec![0];
+        hostile.extend(compress_varint(u128::MAX / 4));
+        hostile.extend([0; 64]);
+        let decompressed = Vec::<u8>::decompress_from_binaries(&mut hostile, None);
+        assert!(matches!(decompressed, Err(DecompressError::WrongBytesLength(_))));
+
+        //a few hundred bytes that expand to 100000, checked before they are inflated
+        let mut codecs = vec![Codec::Lz4];
+        #[cfg(feature = "deflate")]
+        codecs.push(Codec::Deflate { level: 9 });
+        #[cfg(feature = "zstd")]
+        codecs.push(Codec::Zstd { level: 1 });
+        for codec in codecs {
+            let coded = Coded { data: vec![7; 100000], codec };
+            let mut compressed = coded.clone().compress_to_binaries(None).unwrap().to_binaries();
+            assert!(compressed.len() < 100000);
+            comprezable::set_max_decode_len(99999);
+            let decompressed = Coded::decompress_from_binaries(&mut compressed.clone(), None);
+            comprezable::set_max_decode_len(comprezable::DEFAULT_MAX_DECODE_LEN);
+            assert!(matches!(decompressed, Err(DecompressError::LengthLimitExceeded(_))), "{:?}", codec);
+            assert_eq!(Coded::decompress_from_binaries(&mut compressed, None).unwrap().data, coded.data);
+        }
+    }
 }